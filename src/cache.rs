@@ -1,9 +1,12 @@
 use crate::theme::DirectoryRef;
-use crate::{IconFile, Icons, Theme};
+use crate::{FileProvider, IconFile, Icons, LookupProfile, StdFileProvider, Theme};
 use qp_trie::wrapper::BString;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::{OsStr, OsString};
+use std::hash::{BuildHasher, RandomState};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Caching version of [`Icons`].
 ///
@@ -24,6 +27,8 @@ pub struct IconsCache {
     /// also appear in this map. For the same reason, both `icons` and `themes` aren't `pub`;
     /// otherwise users could break that invariant.
     themes: HashMap<OsString, ThemeCache>,
+    /// The most recent results of [`find_icon_strict`](IconsCache::find_icon_strict).
+    results: ResultCache,
 }
 
 impl IconsCache {
@@ -54,19 +59,132 @@ impl IconsCache {
         size: u32,
         scale: u32,
         theme: &str,
+    ) -> Option<IconFile> {
+        self.find_icon_strict(icon_name, size, scale, theme)
+            .or_else(|| {
+                let fallback_icon = self.icons.fallback_icon.clone()?;
+                self.find_icon_strict(&fallback_icon, size, scale, theme)
+            })
+    }
+
+    /// Look up many icons of the same size, scale and theme at once, returning the result for
+    /// each name in `icon_names`, in order.
+    ///
+    /// This behaves like calling [`find_icon`](IconsCache::find_icon) for every name, but only
+    /// resolves which theme to use once. Suited to, for example, rendering a grid of icons.
+    pub fn find_icons(
+        &mut self,
+        icon_names: &[&str],
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Vec<Option<IconFile>> {
+        let IconsCache {
+            icons,
+            themes,
+            results,
+        } = self;
+        let resolved = icons.lookup_theme(theme);
+
+        let mut find_icon_strict = |icon_name: &str| {
+            if icon_name.is_empty() {
+                return None;
+            }
+
+            if let Some(icon) = results.get(icon_name, size, scale, theme) {
+                return icon;
+            }
+
+            let icon = resolved.and_then(|resolved| {
+                Self::find_icon_resolved(icons, themes, resolved, icon_name, size, scale)
+            });
+            results.insert(icon_name, size, scale, theme, icon.clone());
+
+            icon
+        };
+
+        icon_names
+            .iter()
+            .map(|icon_name| {
+                find_icon_strict(icon_name)
+                    .or_else(|| find_icon_strict(icons.fallback_icon.as_deref()?))
+            })
+            .collect()
+    }
+
+    /// Like [`find_icon`](IconsCache::find_icon), but never returns the fallback icon.
+    ///
+    /// Caching version of [`Icons::find_icon_strict`].
+    pub fn find_icon_strict(
+        &mut self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
     ) -> Option<IconFile> {
         if icon_name.is_empty() {
             return None;
         }
 
-        let theme = match self.theme_cache_mut(theme) {
-            Some(theme) => theme,
-            None => self.theme_cache_mut("hicolor")?,
+        if let Some(icon) = self.results.get(icon_name, size, scale, theme) {
+            return icon;
+        }
+
+        let IconsCache { icons, themes, .. } = self;
+        let icon = icons.lookup_theme(theme).and_then(|resolved| {
+            Self::find_icon_resolved(icons, themes, resolved, icon_name, size, scale)
+        });
+        self.results
+            .insert(icon_name, size, scale, theme, icon.clone());
+
+        icon
+    }
+
+    /// Sets how many results of lookups are remembered, 256 by default.
+    ///
+    /// On top of caching the files of each icon per theme, `IconsCache` remembers the final result
+    /// of the most recent lookups, so that repeating the exact same lookup (name, size, scale and
+    /// theme) is nearly free. When more lookups are remembered than `capacity`, the least
+    /// recently used one is forgotten. A capacity of 0 disables remembering results.
+    pub fn set_result_capacity(&mut self, capacity: usize) {
+        self.results.set_capacity(capacity);
+    }
+
+    /// Empties all caches: the remembered results of lookups, and the cache of every theme.
+    pub fn clear_cache(&mut self) {
+        self.results.clear();
+        for theme in self.themes.values_mut() {
+            theme.clear_cache();
+        }
+    }
+
+    /// Looks up `icon_name`, and then its alias, in `theme` as resolved by
+    /// [`Icons::lookup_theme`], using its cache.
+    fn find_icon_resolved(
+        icons: &Icons,
+        themes: &mut HashMap<OsString, ThemeCache>,
+        theme: &Theme,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+    ) -> Option<IconFile> {
+        let mut find_icon_unaliased = |icon_name: &str| {
+            // the embedded theme has no cache
+            let icon = match themes.get_mut(theme.info.internal_name.as_os_str()) {
+                Some(theme_cache) => theme_cache.find_icon(icon_name, size, scale),
+                None => theme.find_icon(icon_name, size, scale),
+            };
+
+            icon.or_else(|| {
+                let mut profile = LookupProfile::default();
+                icons
+                    .find_standalone_or_embedded(icon_name, size, scale, Some(theme), &mut profile)
+                    .map(|(icon, _)| icon)
+            })
         };
 
-        theme
-            .find_icon(icon_name, size, scale)
-            .or_else(|| self.find_standalone_icon(icon_name))
+        find_icon_unaliased(icon_name)
+            .or_else(|| find_icon_unaliased(icons.aliases.get(icon_name)?))
     }
 
     /// Using [`Icons::find_all_icons`], populate the cache with all icons available.
@@ -108,9 +226,16 @@ impl IconsCache {
     }
 
     /// Access, mutably, a known icon theme cache by name.
+    ///
+    /// As the returned cache may be changed, for example by [clearing](ThemeCache::clear_cache) it
+    /// or [importing](ThemeCache::import) a snapshot into it, the results of lookups remembered by
+    /// `IconsCache` are forgotten.
     pub fn theme_cache_mut(&mut self, theme_name: &str) -> Option<&mut ThemeCache> {
         let theme_name: &OsStr = theme_name.as_ref();
-        self.themes.get_mut(theme_name)
+        let theme = self.themes.get_mut(theme_name)?;
+        self.results.clear();
+
+        Some(theme)
     }
 
     /// Look up a standalone icon by name.
@@ -134,7 +259,150 @@ impl From<Icons> for IconsCache {
             .map(|(k, v)| (k.clone(), v.clone().into()))
             .collect();
 
-        Self { icons, themes }
+        Self {
+            icons,
+            themes,
+            results: ResultCache::new(256),
+        }
+    }
+}
+
+/// A remembered result of a lookup with [`IconsCache::find_icon_strict`], along with its
+/// parameters.
+struct CachedResult {
+    icon_name: String,
+    size: u32,
+    scale: u32,
+    theme: String,
+    icon: Option<IconFile>,
+    /// The moment this result was last used, see [`ResultCache::clock`].
+    last_used: u64,
+}
+
+impl CachedResult {
+    fn matches(&self, icon_name: &str, size: u32, scale: u32, theme: &str) -> bool {
+        self.icon_name == icon_name
+            && self.size == size
+            && self.scale == scale
+            && self.theme == theme
+    }
+}
+
+/// A least recently used cache of lookup results.
+///
+/// Results are found by the hash of their parameters, so that lookups don't have to allocate a
+/// key. Results whose parameters hash the same share a bucket.
+struct ResultCache {
+    capacity: usize,
+    /// The results, by the hash of their parameters.
+    entries: HashMap<u64, Vec<CachedResult>>,
+    /// The hash of each result, by the moment it was last used, from least to most recently used.
+    order: BTreeMap<u64, u64>,
+    /// Incremented on every use of the cache, to order entries by their last use.
+    clock: u64,
+    hasher: RandomState,
+}
+
+impl ResultCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    fn get(
+        &mut self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<Option<IconFile>> {
+        let hash = self.hasher.hash_one((icon_name, size, scale, theme));
+        let result = self
+            .entries
+            .get_mut(&hash)?
+            .iter_mut()
+            .find(|result| result.matches(icon_name, size, scale, theme))?;
+
+        self.clock += 1;
+        self.order.remove(&result.last_used);
+        self.order.insert(self.clock, hash);
+        result.last_used = self.clock;
+
+        Some(result.icon.clone())
+    }
+
+    fn insert(
+        &mut self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+        icon: Option<IconFile>,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let hash = self.hasher.hash_one((icon_name, size, scale, theme));
+        self.remove(hash, |result| result.matches(icon_name, size, scale, theme));
+        self.evict((self.len() + 1).saturating_sub(self.capacity));
+
+        self.clock += 1;
+        self.order.insert(self.clock, hash);
+        self.entries.entry(hash).or_default().push(CachedResult {
+            icon_name: icon_name.to_owned(),
+            size,
+            scale,
+            theme: theme.to_owned(),
+            icon,
+            last_used: self.clock,
+        });
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict(self.len().saturating_sub(capacity));
+    }
+
+    /// Removes the `count` least recently used entries.
+    fn evict(&mut self, count: usize) {
+        for _ in 0..count {
+            let Some((&last_used, &hash)) = self.order.first_key_value() else {
+                return;
+            };
+
+            self.remove(hash, |result| result.last_used == last_used);
+        }
+    }
+
+    /// Removes the result with the given hash that `matches`, if any.
+    fn remove(&mut self, hash: u64, matches: impl Fn(&CachedResult) -> bool) {
+        let Some(bucket) = self.entries.get_mut(&hash) else {
+            return;
+        };
+        let Some(position) = bucket.iter().position(matches) else {
+            return;
+        };
+
+        let result = bucket.swap_remove(position);
+        self.order.remove(&result.last_used);
+        if bucket.is_empty() {
+            self.entries.remove(&hash);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
     }
 }
 
@@ -195,16 +463,179 @@ impl ThemeCache {
         let icon = icon_files.iter().min_by_key(|(dir, _)| {
             let dir = &self.theme.info.index.directories[*dir];
 
-            dir.size_distance(size, scale)
+            dir.lookup_order(size, scale)
         });
 
         icon.map(|(_, ico)| ico.clone())
     }
 
+    /// Checks whether the files of an icon are in the internal cache, without populating it.
+    ///
+    /// Icons are cached by [`find_icon_here`](ThemeCache::find_icon_here), even if no files were
+    /// found for them.
+    pub fn is_cached(&self, icon_name: &str) -> bool {
+        self.cache.contains_key_str(icon_name)
+    }
+
     /// Empties the internal cache.
     pub fn clear_cache(&mut self) {
         self.cache.clear();
     }
+
+    /// Takes a snapshot of the internal cache, for example to persist it across restarts of the
+    /// program and [`import`](ThemeCache::import) it again later.
+    ///
+    /// The snapshot also records when the theme's directories were last modified, so that it can
+    /// be [checked for staleness](CacheSnapshot::is_stale). Icons held in memory instead of on
+    /// disk are left out.
+    pub fn export(&self) -> CacheSnapshot {
+        let info = &self.theme.info;
+
+        let icons = self
+            .cache
+            .iter()
+            .map(|(icon_name, files)| {
+                let files = files
+                    .iter()
+                    .filter_map(|(dir, icon)| Some((*dir, icon.path()?.to_owned())))
+                    .collect();
+
+                (icon_name.as_str().to_owned(), files)
+            })
+            .collect();
+
+        let directory_paths = info.index.directories.iter().flat_map(|dir| {
+            info.base_dirs
+                .iter()
+                .map(|base_dir| base_dir.join(&dir.directory_name))
+        });
+
+        CacheSnapshot {
+            theme: info.internal_name.to_string_lossy().into_owned(),
+            directories: directory_names(&self.theme),
+            modified: directory_paths
+                .map(|path| {
+                    let modified = self.theme.file_provider.modified(&path);
+                    (path, modified)
+                })
+                .collect(),
+            icons,
+        }
+    }
+
+    /// Adds the icons of a [snapshot](ThemeCache::export) to the internal cache, replacing the
+    /// files cached for icons in both.
+    ///
+    /// The snapshot isn't checked for staleness; use [`CacheSnapshot::is_stale`] for that.
+    /// Returns an error, leaving the cache as-is, if the snapshot was taken of another theme, if
+    /// the theme's directories have changed since, or if it refers to a directory the theme
+    /// doesn't have.
+    pub fn import(&mut self, snapshot: CacheSnapshot) -> Result<(), SnapshotError> {
+        if OsStr::new(&snapshot.theme) != self.theme.info.internal_name {
+            return Err(SnapshotError::OtherTheme(snapshot.theme));
+        }
+
+        // the files refer to directories by their index, which is only valid for the same list
+        if snapshot.directories != directory_names(&self.theme) {
+            return Err(SnapshotError::DirectoriesChanged);
+        }
+
+        // a snapshot may have been edited or corrupted after it was persisted
+        let directory_count = self.theme.info.index.directories.len();
+        let invalid_directory = snapshot
+            .icons
+            .iter()
+            .flat_map(|(_, files)| files)
+            .map(|(dir, _)| *dir)
+            .find(|dir| *dir >= directory_count);
+        if let Some(dir) = invalid_directory {
+            return Err(SnapshotError::InvalidDirectory(dir));
+        }
+
+        for (icon_name, files) in snapshot.icons {
+            let files = files
+                .into_iter()
+                .filter_map(|(dir, path)| Some((dir, IconFile::from_path_buf(path)?)))
+                .collect();
+
+            self.cache.insert(icon_name.into(), files);
+        }
+
+        Ok(())
+    }
+
+    /// Like [`CacheSnapshot::is_stale`], but checks the directories through the theme's
+    /// [`FileProvider`] rather than the file system.
+    pub fn is_snapshot_stale(&self, snapshot: &CacheSnapshot) -> bool {
+        snapshot.is_stale_with(self.theme.file_provider.as_ref())
+    }
+}
+
+/// The names of the directories of a theme, which [`DirectoryRef`]s index into.
+fn directory_names(theme: &Theme) -> Vec<String> {
+    theme
+        .info
+        .index
+        .directories
+        .iter()
+        .map(|dir| dir.directory_name.clone())
+        .collect()
+}
+
+/// The contents of a [`ThemeCache`], taken with [`ThemeCache::export`] to be restored with
+/// [`ThemeCache::import`].
+///
+/// With the `serde` feature, snapshots can be serialized to persist them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CacheSnapshot {
+    /// The internal name of the theme.
+    theme: String,
+    /// The names of the theme's directories, at the time of the snapshot.
+    directories: Vec<String>,
+    /// The paths of the theme's directories, and when they were last modified.
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+    /// The cached icon names, with the directories and paths of their files.
+    icons: Vec<(String, Vec<(DirectoryRef, PathBuf)>)>,
+}
+
+impl CacheSnapshot {
+    /// Checks whether any of the theme's directories was modified (or created, or removed) since
+    /// this snapshot was taken, in which case it may hold outdated files.
+    ///
+    /// Adding or removing icons modifies the directory they're in, so a snapshot that isn't stale
+    /// holds the same files as a freshly populated cache would.
+    ///
+    /// The directories are checked on the file system, through [`StdFileProvider`]. For themes
+    /// read through another [`FileProvider`], use [`ThemeCache::is_snapshot_stale`].
+    pub fn is_stale(&self) -> bool {
+        self.is_stale_with(&StdFileProvider)
+    }
+
+    pub(crate) fn is_stale_with(&self, file_provider: &dyn FileProvider) -> bool {
+        self.modified
+            .iter()
+            .any(|(path, last_modified)| file_provider.modified(path) != *last_modified)
+    }
+
+    /// The internal name of the theme this snapshot was taken of.
+    pub fn theme(&self) -> &str {
+        &self.theme
+    }
+}
+
+/// An error returned by [`ThemeCache::import`].
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    /// The snapshot was taken of the theme with this internal name.
+    #[error("the snapshot was taken of another theme, `{0}`")]
+    OtherTheme(String),
+    /// The theme's directories have changed since the snapshot was taken.
+    #[error("the theme's directories have changed since the snapshot was taken")]
+    DirectoriesChanged,
+    /// The snapshot refers to a directory by an index the theme has no directory at.
+    #[error("the snapshot refers to directory {0}, which the theme doesn't have")]
+    InvalidDirectory(DirectoryRef),
 }
 
 impl From<Arc<Theme>> for ThemeCache {
@@ -219,8 +650,11 @@ impl From<Arc<Theme>> for ThemeCache {
 #[cfg(test)]
 mod test {
     use std::ffi::OsString;
-    use crate::cache::{IconsCache, ThemeCache};
-    use crate::search::test::test_search;
+    use std::path::Path;
+    use std::time::{Instant, SystemTime};
+    use crate::cache::{IconsCache, SnapshotError, ThemeCache};
+    use crate::search::test::{test_dir, test_search, write_theme};
+    use crate::{FileEntry, FileProvider, IconSearch, StdFileProvider};
 
     #[test]
     fn test_icons_cached() {
@@ -232,6 +666,142 @@ mod test {
         assert_eq!(icon_original, icon_cached);
     }
 
+    #[test]
+    fn test_icons_cached_alias() {
+        let mut icons = test_search().search().icons();
+        icons.add_alias("smiley", "happy");
+
+        let mut icons_cache: IconsCache = icons.into();
+        let icon = icons_cache.find_icon("smiley", 16, 1, "TestTheme").unwrap();
+
+        assert_eq!(icon.icon_name(), "happy");
+    }
+
+    #[test]
+    fn test_icons_cached_fallback() {
+        let mut icons = test_search().search().icons();
+        icons.set_fallback_icon(Some("pixel"));
+
+        let mut icons_cache: IconsCache = icons.into();
+        let icon = icons_cache
+            .find_icon("does-not-exist", 16, 1, "TestTheme")
+            .unwrap();
+
+        assert_eq!(icon.icon_name(), "pixel");
+        assert!(
+            icons_cache
+                .find_icon_strict("does-not-exist", 16, 1, "TestTheme")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_icons_cached_batch() {
+        let mut icons_cache = test_search().search().icons_cached();
+
+        let icons = icons_cache.find_icons(&["happy", "missing", "pixel"], 16, 1, "TestTheme");
+        assert_eq!(icons.len(), 3);
+        assert_eq!(icons[0].as_ref().unwrap().icon_name(), "happy");
+        assert!(icons[1].is_none());
+        assert_eq!(icons[2].as_ref().unwrap().icon_name(), "pixel");
+
+        let theme_cache = icons_cache.theme_cache("TestTheme").unwrap();
+        assert!(theme_cache.is_cached("happy") && theme_cache.is_cached("missing"));
+
+        // aliases and the fallback icon apply to every name
+        let mut icons = test_search().search().icons();
+        icons.add_alias("smile", "happy");
+        icons.set_fallback_icon(Some("pixel"));
+        let mut icons_cache = IconsCache::from_icons(icons);
+        let icons = icons_cache.find_icons(&["smile", "missing"], 16, 1, "TestTheme");
+        assert_eq!(icons[0].as_ref().unwrap().icon_name(), "happy");
+        assert_eq!(icons[1].as_ref().unwrap().icon_name(), "pixel");
+    }
+
+    #[test]
+    fn test_icons_cached_results_speed() {
+        const LOOKUPS: u32 = 10_000;
+
+        let mut icons_cache = test_search().search().icons_cached();
+        let time_lookups = |icons_cache: &mut IconsCache| {
+            // fill the caches, so that only hits are timed
+            let expected = icons_cache.find_icon("happy", 20, 1, "TestTheme");
+            assert!(expected.is_some());
+
+            let then = Instant::now();
+            for _ in 0..LOOKUPS {
+                assert_eq!(icons_cache.find_icon("happy", 20, 1, "TestTheme"), expected);
+            }
+            then.elapsed() / LOOKUPS
+        };
+
+        let with_results = time_lookups(&mut icons_cache);
+        // without remembered results, only the files of each icon are cached
+        icons_cache.set_result_capacity(0);
+        let without_results = time_lookups(&mut icons_cache);
+
+        println!(
+            "avg {with_results:?} per lookup with remembered results, {without_results:?} with \
+             only the per-name cache"
+        );
+    }
+
+    #[test]
+    fn test_icons_cached_results() {
+        let mut icons_cache = test_search().search().icons_cached();
+        icons_cache.set_result_capacity(2);
+
+        let happy = icons_cache.find_icon("happy", 16, 1, "TestTheme");
+        assert!(happy.is_some());
+        assert!(
+            icons_cache
+                .find_icon("missing", 16, 1, "TestTheme")
+                .is_none()
+        );
+        // a hit makes `happy` the most recently used result
+        assert_eq!(icons_cache.find_icon("happy", 16, 1, "TestTheme"), happy);
+        icons_cache.find_icon("pixel", 16, 1, "TestTheme");
+
+        let remembered = |icons_cache: &IconsCache| {
+            let mut remembered = icons_cache
+                .results
+                .entries
+                .values()
+                .flatten()
+                .map(|result| result.icon_name.clone())
+                .collect::<Vec<_>>();
+            remembered.sort();
+            remembered
+        };
+        assert_eq!(remembered(&icons_cache), ["happy", "pixel"]);
+
+        icons_cache.set_result_capacity(1);
+        assert_eq!(remembered(&icons_cache), ["pixel"]);
+        assert_eq!(icons_cache.results.len(), 1);
+
+        // the theme caches may be changed through `theme_cache_mut`
+        icons_cache
+            .theme_cache_mut("TestTheme")
+            .unwrap()
+            .clear_cache();
+        assert_eq!(icons_cache.results.len(), 0);
+        assert_eq!(icons_cache.find_icon("happy", 16, 1, "TestTheme"), happy);
+
+        icons_cache.clear_cache();
+        assert!(icons_cache.results.entries.is_empty());
+        assert!(
+            !icons_cache
+                .theme_cache("TestTheme")
+                .unwrap()
+                .is_cached("happy")
+        );
+
+        icons_cache.set_result_capacity(0);
+        assert_eq!(icons_cache.find_icon("happy", 16, 1, "TestTheme"), happy);
+        assert!(icons_cache.results.entries.is_empty());
+        assert!(icons_cache.results.order.is_empty());
+    }
+
     #[test]
     fn test_cached_entry_persists() {
         let icons = test_search().search().icons();
@@ -242,6 +812,7 @@ mod test {
         let mut theme_cache: ThemeCache = theme.into();
 
         assert!(theme_cache.cache.is_empty(), "cache is not yet populated");
+        assert!(!theme_cache.is_cached("happy"));
 
         let icon = theme_cache.find_icon_here("happy", 16, 1).unwrap();
         assert_eq!(icon.icon_name(), "happy");
@@ -251,6 +822,7 @@ mod test {
             theme_cache.cache.contains_key_str("happy"),
             "cache contains happy icon"
         );
+        assert!(theme_cache.is_cached("happy"));
 
         let icon_cached = theme_cache.find_icon_here("happy", 16, 1).unwrap();
 
@@ -277,4 +849,117 @@ mod test {
         assert_eq!(icons.themes[&OsString::from("TestTheme")].cache.count(), 2);
         assert_eq!(icons.themes[&OsString::from("OtherTheme")].cache.count(), 1);
     }
+
+    #[test]
+    fn test_cache_snapshot() {
+        let icons = test_search().search().icons();
+        let mut theme_cache = ThemeCache::from_theme(icons.theme("TestTheme").unwrap());
+        let happy = theme_cache.find_icon_here("happy", 16, 1);
+        assert!(happy.is_some());
+        assert!(theme_cache.find_icon_here("missing", 16, 1).is_none());
+
+        let snapshot = theme_cache.export();
+        assert_eq!(snapshot.theme(), "TestTheme");
+        assert!(!snapshot.is_stale());
+
+        let mut restored = ThemeCache::from_theme(icons.theme("TestTheme").unwrap());
+        restored.import(snapshot.clone()).unwrap();
+        assert!(restored.is_cached("happy") && restored.is_cached("missing"));
+        assert_eq!(restored.find_icon_here("happy", 16, 1), happy);
+        assert_eq!(
+            restored.find_icon_here("happy", 32, 1),
+            theme_cache.find_icon_here("happy", 32, 1)
+        );
+        assert_eq!(restored.export(), snapshot);
+
+        let mut other = ThemeCache::from_theme(icons.theme("OtherTheme").unwrap());
+        assert!(matches!(
+            other.import(snapshot),
+            Err(SnapshotError::OtherTheme(theme)) if theme == "TestTheme"
+        ));
+        assert!(!other.is_cached("happy"));
+    }
+
+    #[test]
+    fn test_cache_snapshot_stale() {
+        let base_dir = test_dir("cache-snapshot");
+        write_theme(
+            &base_dir,
+            "Stale",
+            "[Icon Theme]\nName=Stale\nDirectories=16x16,32x32\n\n\
+             [16x16]\nSize=16\n\n[32x32]\nSize=32\n",
+        );
+        std::fs::create_dir_all(base_dir.join("Stale/16x16")).unwrap();
+        std::fs::write(base_dir.join("Stale/16x16/icon.png"), "").unwrap();
+
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        let mut theme_cache = ThemeCache::from_theme(icons.theme("Stale").unwrap());
+        assert!(theme_cache.find_icon_here("icon", 32, 1).is_some());
+
+        let snapshot = theme_cache.export();
+        assert!(!snapshot.is_stale());
+
+        // a larger version of the icon is added
+        std::fs::create_dir_all(base_dir.join("Stale/32x32")).unwrap();
+        std::fs::write(base_dir.join("Stale/32x32/icon.png"), "").unwrap();
+        assert!(snapshot.is_stale());
+        assert!(theme_cache.is_snapshot_stale(&snapshot));
+    }
+
+    #[test]
+    fn test_cache_snapshot_file_provider() {
+        /// Reads the file system, but reports every path as modified at the same time.
+        struct FrozenClock;
+
+        impl FileProvider for FrozenClock {
+            fn exists(&self, path: &Path) -> bool {
+                StdFileProvider.exists(path)
+            }
+
+            fn read_dir(&self, path: &Path) -> std::io::Result<Vec<FileEntry>> {
+                StdFileProvider.read_dir(path)
+            }
+
+            fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+                StdFileProvider.read(path)
+            }
+
+            fn modified(&self, _path: &Path) -> Option<SystemTime> {
+                Some(SystemTime::UNIX_EPOCH)
+            }
+        }
+
+        let icons = test_search()
+            .with_file_provider(FrozenClock)
+            .search()
+            .icons();
+        let mut theme_cache = ThemeCache::from_theme(icons.theme("TestTheme").unwrap());
+        theme_cache.find_icon_here("happy", 16, 1);
+
+        let snapshot = theme_cache.export();
+        assert!(!theme_cache.is_snapshot_stale(&snapshot));
+        // the file system itself has other modification times
+        assert!(snapshot.is_stale());
+    }
+
+    #[test]
+    fn test_cache_snapshot_invalid_directory() {
+        let icons = test_search().search().icons();
+        let mut theme_cache = ThemeCache::from_theme(icons.theme("TestTheme").unwrap());
+        theme_cache.find_icon_here("happy", 16, 1);
+
+        let mut snapshot = theme_cache.export();
+        let (_, files) = &mut snapshot.icons[0];
+        let path = files[0].1.clone();
+        files.push((snapshot.directories.len(), path));
+
+        let mut restored = ThemeCache::from_theme(icons.theme("TestTheme").unwrap());
+        assert!(matches!(
+            restored.import(snapshot),
+            Err(SnapshotError::InvalidDirectory(dir)) if dir == restored.theme.info.index.directories.len()
+        ));
+        assert!(!restored.is_cached("happy"));
+    }
 }
@@ -0,0 +1,205 @@
+use crate::theme::find_attr;
+use freedesktop_entry_parser::low_level::SectionBytes;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Extra data about an icon, read from the `.icon` file next to it.
+///
+/// Icon files may be accompanied by a file of the same name with the `.icon` extension, holding
+/// an `[Icon Data]` section. See [`IconFile::metadata_file`](crate::IconFile::metadata_file).
+///
+/// All doc comments in *italics* below are copy-pasted from the XDG Icon Theme Specification.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IconData {
+    /// *If the icon is used to display a text, this is the rectangle in which the text is
+    /// displayed.*
+    ///
+    /// Given as `[x0, y0, x1, y1]`, in a coordinate system where the icon is 1000 units wide and
+    /// high.
+    pub embedded_text_rectangle: Option<[i32; 4]>,
+    /// *Points that can be used to attach "emblems" to the icon.*
+    ///
+    /// Given as `(x, y)` pairs, in the same coordinate system as the
+    /// [`embedded_text_rectangle`](IconData::embedded_text_rectangle).
+    pub attach_points: Vec<(i32, i32)>,
+    /// The unlocalized `DisplayName`: *a translated UTF8 string that can be displayed instead of
+    /// the icon name.*
+    pub display_name: Option<String>,
+    /// The localized `DisplayName`s, by locale (e.g. `de` for `DisplayName[de]`).
+    pub localized_display_names: HashMap<String, String>,
+}
+
+/// Errors that may occur while parsing an `.icon` file.
+#[derive(Debug, thiserror::Error)]
+pub enum IconDataParseError {
+    /// Missing the "Icon Data" section.
+    #[error("missing Icon Data section")]
+    NotIconData,
+    /// The file isn't encoded in UTF-8.
+    #[error("the input wasn't in utf-8")]
+    NotUtf8(#[from] std::str::Utf8Error),
+    /// Couldn't parse a number where one was expected.
+    #[error("a number was expected but failed to parse")]
+    ParseNumError(#[from] std::num::ParseIntError),
+    /// The `EmbeddedTextRectangle` doesn't consist of exactly four numbers.
+    #[error("the embedded text rectangle must consist of four numbers")]
+    InvalidRectangle,
+    /// One of the `AttachPoints` isn't a pair of numbers.
+    #[error("an attach point must consist of two numbers")]
+    InvalidAttachPoint,
+    /// The file was not properly formatted as a freedesktop entry file.
+    #[error("invalid format for a freedesktop entry file")]
+    ParseError(#[from] freedesktop_entry_parser::low_level::ParseError),
+}
+
+impl IconData {
+    /// Parse icon data from the content, in bytes, of an `.icon` file.
+    ///
+    /// Only the `[Icon Data]` section is read; it has to be the first section of the file.
+    pub fn parse(bytes: &[u8]) -> Result<Self, IconDataParseError> {
+        let mut entry = freedesktop_entry_parser::low_level::parse_entry(bytes);
+
+        let section: SectionBytes = entry.next().ok_or(IconDataParseError::NotIconData)??;
+        if section.title != b"Icon Data" {
+            return Err(IconDataParseError::NotIconData);
+        }
+
+        let embedded_text_rectangle = find_attr(&section, "EmbeddedTextRectangle")?
+            .map(|s| {
+                let numbers = parse_numbers(s)?;
+                numbers
+                    .try_into()
+                    .map_err(|_| IconDataParseError::InvalidRectangle)
+            })
+            .transpose()?;
+
+        // attach points are separated by `|`, and are each an `x,y` pair
+        let attach_points = find_attr(&section, "AttachPoints")?
+            .into_iter()
+            .flat_map(|s| s.split('|'))
+            .filter(|point| !point.trim().is_empty())
+            .map(|point| match parse_numbers(point)?[..] {
+                [x, y] => Ok((x, y)),
+                _ => Err(IconDataParseError::InvalidAttachPoint),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let display_name = find_attr(&section, "DisplayName")?.map(Into::into);
+        let localized_display_names = section
+            .attrs
+            .iter()
+            .filter_map(|attr| {
+                let param = attr.param.as_ref()?;
+                (param.attr_name == b"DisplayName").then_some((param.param, &attr.value))
+            })
+            .map(|(locale, value)| {
+                Ok((
+                    str::from_utf8(locale)?.into(),
+                    str::from_utf8(value)?.into(),
+                ))
+            })
+            .collect::<Result<_, std::str::Utf8Error>>()?;
+
+        Ok(Self {
+            embedded_text_rectangle,
+            attach_points,
+            display_name,
+            localized_display_names,
+        })
+    }
+
+    /// Read and parse the `.icon` file at `path`. See [`parse`](IconData::parse).
+    pub fn parse_from_file(path: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let data = IconData::parse(&bytes).map_err(std::io::Error::other)?;
+
+        Ok(data)
+    }
+
+    /// Returns the display name for the given locale (e.g. `de`), or the unlocalized display
+    /// name if there is no translation for it.
+    pub fn display_name_for(&self, locale: &str) -> Option<&str> {
+        self.localized_display_names
+            .get(locale)
+            .or(self.display_name.as_ref())
+            .map(String::as_str)
+    }
+}
+
+/// Parses a comma-separated list of numbers.
+fn parse_numbers(s: &str) -> Result<Vec<i32>, IconDataParseError> {
+    let numbers = s
+        .split(',')
+        .map(|n| n.trim().parse())
+        .collect::<Result<_, _>>()?;
+
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::search::test::test_dir;
+    use crate::{IconData, IconDataParseError, IconFile};
+    use std::error::Error;
+
+    #[test]
+    fn test_parse_icon_data() -> Result<(), Box<dyn Error>> {
+        let data = IconData::parse(
+            b"[Icon Data]\nEmbeddedTextRectangle=100,100,900,900\n\
+              AttachPoints=0,0|1000, 1000|\nDisplayName=Folder\nDisplayName[de]=Ordner\n",
+        )?;
+
+        assert_eq!(data.embedded_text_rectangle, Some([100, 100, 900, 900]));
+        assert_eq!(data.attach_points, [(0, 0), (1000, 1000)]);
+        assert_eq!(data.display_name.as_deref(), Some("Folder"));
+        assert_eq!(data.display_name_for("de"), Some("Ordner"));
+        assert_eq!(data.display_name_for("fr"), Some("Folder"));
+
+        // all keys are optional
+        assert_eq!(
+            IconData::parse(b"[Icon Data]\nUnrelated=key\n")?,
+            IconData::default()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid_icon_data() {
+        assert!(matches!(
+            IconData::parse(b"[Icon Theme]\nName=Theme\n"),
+            Err(IconDataParseError::NotIconData)
+        ));
+        assert!(matches!(
+            IconData::parse(b"[Icon Data]\nEmbeddedTextRectangle=1,2,3\n"),
+            Err(IconDataParseError::InvalidRectangle)
+        ));
+        assert!(matches!(
+            IconData::parse(b"[Icon Data]\nAttachPoints=1,2|3\n"),
+            Err(IconDataParseError::InvalidAttachPoint)
+        ));
+        assert!(matches!(
+            IconData::parse(b"[Icon Data]\nAttachPoints=a,b\n"),
+            Err(IconDataParseError::ParseNumError(_))
+        ));
+    }
+
+    #[test]
+    fn test_metadata_file() -> Result<(), Box<dyn Error>> {
+        let dir = test_dir("metadata-file");
+        std::fs::write(dir.join("folder.png"), "")?;
+        std::fs::write(dir.join("folder.icon"), "[Icon Data]\nDisplayName=Folder\n")?;
+        std::fs::write(dir.join("plain.png"), "")?;
+
+        let icon = IconFile::from_path(&dir.join("folder.png")).unwrap();
+        let metadata_file = icon.metadata_file().unwrap();
+        assert_eq!(metadata_file, dir.join("folder.icon"));
+        let data = IconData::parse_from_file(&metadata_file)?;
+        assert_eq!(data.display_name.as_deref(), Some("Folder"));
+
+        let icon = IconFile::from_path(&dir.join("plain.png")).unwrap();
+        assert_eq!(icon.metadata_file(), None);
+
+        Ok(())
+    }
+}
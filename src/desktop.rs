@@ -0,0 +1,87 @@
+use crate::{IconFile, Icons};
+use std::path::Path;
+
+impl Icons {
+    /// Resolve the value of the `Icon` key of a desktop entry to an icon file.
+    ///
+    /// From the Desktop Entry specification: *"If the name is an absolute path, the given file
+    /// will be used. If the name is not an absolute path, the algorithm described in the Icon
+    /// Theme Specification will be used to locate the icon."*
+    ///
+    /// Absolute paths are returned as-is if they point to an existing file with an icon extension,
+    /// otherwise the [fallback icon](Icons::set_fallback_icon) is returned, if there is one.
+    /// All other values are looked up with [`find_icon`](Icons::find_icon).
+    #[cfg_attr(docsrs, doc(cfg(feature = "desktop")))]
+    pub fn resolve_desktop_icon(
+        &self,
+        icon_field: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<IconFile> {
+        let path = Path::new(icon_field);
+
+        if path.is_absolute() {
+            if self.file_provider.is_file(path)
+                && let Some(icon) = IconFile::from_path(path)
+            {
+                return Some(icon);
+            }
+
+            let fallback_icon = self.fallback_icon.as_deref()?;
+            return self.find_icon_strict(fallback_icon, size, scale, theme);
+        }
+
+        self.find_icon(icon_field, size, scale, theme)
+    }
+
+    /// Resolve the `Icon` values of many desktop entries at once, for example to show all
+    /// applications in a launcher.
+    ///
+    /// The returned list contains the result of [`resolve_desktop_icon`](Icons::resolve_desktop_icon)
+    /// for each entry, in order.
+    #[cfg_attr(docsrs, doc(cfg(feature = "desktop")))]
+    pub fn resolve_entries<'a>(
+        &self,
+        entries: impl IntoIterator<Item = &'a str>,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Vec<Option<IconFile>> {
+        entries
+            .into_iter()
+            .map(|icon_field| self.resolve_desktop_icon(icon_field, size, scale, theme))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::search::test::test_search;
+    use std::path::Path;
+
+    #[test]
+    fn test_resolve_entries() {
+        let icons = test_search().search().icons();
+        let pixel = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/test_icons_alt/OtherTheme/1x1/pixel.png");
+
+        let resolved = icons.resolve_entries(
+            [
+                "happy",
+                pixel.to_str().unwrap(),
+                "/does/not/exist.png",
+                "does-not-exist",
+            ],
+            16,
+            1,
+            "TestTheme",
+        );
+
+        assert_eq!(resolved.len(), 4);
+        assert_eq!(resolved[0].as_ref().unwrap().icon_name(), "happy");
+        assert_eq!(resolved[1].as_ref().unwrap().path().unwrap(), pixel);
+        assert!(resolved[2].is_none());
+        assert!(resolved[3].is_none());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{FileEntry, FileProvider, Icons, Theme, ThemeIndex, ThemeInfo};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// The internal name of the theme holding the embedded icons.
+const THEME_NAME: &str = "embedded";
+
+/// The directory the embedded theme pretends to live in. It doesn't exist on disk.
+const THEME_DIR: &str = "/embedded-icons";
+
+/// The only directory of the embedded theme, holding all of its (scalable) icons.
+const ICONS_DIR: &str = "/embedded-icons/scalable";
+
+const INDEX: &[u8] = b"[Icon Theme]\n\
+    Name=Embedded\n\
+    Comment=Fallback icons embedded in the icon crate\n\
+    Directories=scalable\n\n\
+    [scalable]\n\
+    Size=48\n\
+    MinSize=1\n\
+    MaxSize=512\n\
+    Type=Scalable\n";
+
+/// The embedded icons, by file name.
+const ICONS: &[(&str, &[u8])] = &[
+    (
+        "application-x-executable.svg",
+        include_bytes!("embedded/application-x-executable.svg"),
+    ),
+    ("folder.svg", include_bytes!("embedded/folder.svg")),
+    (
+        "image-missing.svg",
+        include_bytes!("embedded/image-missing.svg"),
+    ),
+    (
+        "text-x-generic.svg",
+        include_bytes!("embedded/text-x-generic.svg"),
+    ),
+];
+
+impl Icons {
+    /// Enables a small set of icons that are embedded in the program, to fall back to when an icon
+    /// can't be found on disk.
+    ///
+    /// This is meant for self-contained programs that may run on systems without any icon theme
+    /// installed. The embedded icons are scalable (SVG) icons for the following names:
+    /// - `application-x-executable`
+    /// - `folder`
+    /// - `image-missing`
+    /// - `text-x-generic`
+    ///
+    /// They are checked by [`find_icon`](Icons::find_icon) (and the lookups built on it, like
+    /// [`find_default_icon`](Icons::find_default_icon)) and its variants, like
+    /// [`find_scalable_icon`](Icons::find_scalable_icon), after the themes and standalone icons,
+    /// like a theme all other themes inherit from. That theme is also used if neither the
+    /// requested theme nor `hicolor` exist.
+    ///
+    /// Embedded icons don't exist on disk: they are [held in memory](crate::IconFile::from_bytes),
+    /// and have no path. Use [`IconFile::read`](crate::IconFile::read) to get their contents.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use icon::IconSearch;
+    ///
+    /// let icons = IconSearch::new_empty().search().icons().with_embedded_fallback();
+    /// let icon = icons.find_default_icon("folder", 48, 1).unwrap();
+    /// assert!(icon.bytes().is_some());
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "embedded-fallback")))]
+    pub fn with_embedded_fallback(mut self) -> Self {
+        self.embedded_fallback = true;
+
+        self
+    }
+}
+
+/// Returns the theme holding the embedded icons.
+pub(crate) fn theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+
+    THEME.get_or_init(|| {
+        let info = ThemeInfo {
+            internal_name: THEME_NAME.into(),
+            base_dirs: vec![THEME_DIR.into()],
+            index_location: Path::new(THEME_DIR).join("index.theme"),
+            index: ThemeIndex::parse(INDEX).expect("the embedded index is valid"),
+        };
+
+        Theme::with_file_provider(info, vec![], Arc::new(EmbeddedFileProvider))
+    })
+}
+
+/// Serves the embedded icons from their made-up paths.
+struct EmbeddedFileProvider;
+
+impl EmbeddedFileProvider {
+    fn icon(path: &Path) -> Option<&'static [u8]> {
+        if path.parent()? != Path::new(ICONS_DIR) {
+            return None;
+        }
+
+        let file_name = path.file_name()?;
+        ICONS
+            .iter()
+            .find(|(name, _)| file_name == *name)
+            .map(|(_, data)| *data)
+    }
+}
+
+impl FileProvider for EmbeddedFileProvider {
+    fn exists(&self, path: &Path) -> bool {
+        path == Path::new(THEME_DIR) || path == Path::new(ICONS_DIR) || Self::icon(path).is_some()
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FileEntry>> {
+        let entries = match path.to_str() {
+            Some(THEME_DIR) => vec![FileEntry {
+                path: ICONS_DIR.into(),
+                is_dir: true,
+            }],
+            Some(ICONS_DIR) => ICONS
+                .iter()
+                .map(|(name, _)| FileEntry {
+                    path: Path::new(ICONS_DIR).join(name),
+                    is_dir: false,
+                })
+                .collect(),
+            _ => return Err(io::ErrorKind::NotFound.into()),
+        };
+
+        Ok(entries)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        Self::icon(path)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn embedded_contents(&self, path: &Path) -> Option<&'static [u8]> {
+        Self::icon(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::search::test::{test_dir, test_search};
+    use crate::{FileType, FindError, IconSearch, IconSource};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_embedded_fallback() {
+        let icons = IconSearch::new_empty().search().icons();
+        assert!(icons.find_default_icon("folder", 48, 1).is_none());
+
+        let icons = icons.with_embedded_fallback();
+        let icon = icons.find_default_icon("folder", 48, 1).unwrap();
+        assert_eq!(icon.file_type(), FileType::Svg);
+        assert_eq!(icon.icon_name(), "folder");
+        assert_eq!(icon.path(), None);
+        assert_eq!(
+            icon.bytes(),
+            Some(&include_bytes!("embedded/folder.svg")[..])
+        );
+        assert_eq!(icon.read().unwrap(), icon.bytes().unwrap());
+        assert!(icons.find_default_icon("missing", 48, 1).is_none());
+
+        let icon = icons.find_icon_with_defaults("folder", 48, 1, "Missing");
+        assert!(icon.unwrap().bytes().is_some());
+        assert!(icons.best_icon("folder", 48, 1).unwrap().bytes().is_some());
+
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let icon = icons.find_icon_deadline("folder", 48, 1, "hicolor", deadline);
+        assert!(icon.unwrap().bytes().is_some());
+    }
+
+    #[test]
+    fn test_embedded_fallback_variants() {
+        // without any themes, the embedded theme is used in their stead
+        let icons = IconSearch::new_empty()
+            .search()
+            .icons()
+            .with_embedded_fallback();
+        let scalable = icons.find_scalable_icon("folder", "hicolor").unwrap();
+        assert!(scalable.bytes().is_some());
+        let icon = icons.find_icon_checked("folder", 48, 1, "hicolor");
+        assert!(icon.unwrap().bytes().is_some());
+        let icon = icons.find_icon_within("folder", 48, 1, "hicolor", 0);
+        assert!(icon.unwrap().bytes().is_some());
+        let icon = icons.find_icon_for_scale("folder", 48, 1.5, "hicolor");
+        assert!(icon.unwrap().bytes().is_some());
+        assert!(icons.has_icon("folder", "hicolor"));
+        let icon = icons.find_icon_exact("folder", 48, 1, "hicolor");
+        assert!(icon.unwrap().bytes().is_some());
+        let icon = icons.find_icon_no_upscale("folder", 48, 1, "hicolor");
+        assert!(icon.unwrap().bytes().is_some());
+        let matches = icons
+            .find_icons_matching("f*", "hicolor")
+            .collect::<Vec<_>>();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].0, "folder");
+        assert!(matches[0].1.bytes().is_some());
+
+        // otherwise, it comes after the themes
+        let icons = test_search().search().icons().with_embedded_fallback();
+        let icon = icons.find_scalable_icon("folder", "TestTheme");
+        assert_eq!(icon, Some(scalable));
+        let icon = icons.find_icon_checked("folder", 48, 1, "TestTheme");
+        assert!(icon.unwrap().bytes().is_some());
+        let icon = icons.find_icon_within("folder", 48, 1, "TestTheme", 0);
+        assert!(icon.unwrap().bytes().is_some());
+        assert!(icons.has_icon("folder", "TestTheme"));
+        let icon = icons.find_icon_exact("folder", 48, 1, "TestTheme");
+        assert!(icon.unwrap().bytes().is_some());
+        let icon = icons.find_icon_no_upscale("folder", 48, 1, "TestTheme");
+        assert!(icon.unwrap().bytes().is_some());
+        let names = icons
+            .find_icons_matching("*", "TestTheme")
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert!(names.iter().any(|name| name == "happy"));
+        assert!(names.iter().any(|name| name == "folder"));
+        assert!(!icons.has_icon("missing", "TestTheme"));
+    }
+
+    #[test]
+    fn test_embedded_fallback_try_find_icon() {
+        let icons = IconSearch::new_empty().search().icons();
+        assert_eq!(
+            icons.try_find_icon("folder", 48, 1, "hicolor"),
+            Err(FindError::ThemeNotFound)
+        );
+
+        let icons = icons.with_embedded_fallback();
+        let icon = icons.try_find_icon("folder", 48, 1, "hicolor").unwrap();
+        assert!(icon.bytes().is_some());
+        assert_eq!(
+            icons.try_find_icon("missing", 48, 1, "hicolor"),
+            Err(FindError::IconNotFound)
+        );
+    }
+
+    #[test]
+    fn test_embedded_fallback_order() {
+        let dir = test_dir("embedded-fallback");
+        std::fs::write(dir.join("folder.png"), "").unwrap();
+
+        let icons = test_search()
+            .add_directories([&dir])
+            .search()
+            .icons()
+            .with_embedded_fallback();
+
+        // icons on disk are preferred
+        let icon = icons.find_icon("folder", 48, 1, "TestTheme").unwrap();
+        assert_eq!(icon.path(), Some(&*dir.join("folder.png")));
+        assert_eq!(icon.bytes(), None);
+
+        let (icon, source) = icons
+            .find_icon_source("text-x-generic", 48, 1, "TestTheme")
+            .unwrap();
+        assert!(icon.bytes().is_some());
+        assert_eq!(source, IconSource::Theme("embedded".into()));
+    }
+
+    #[test]
+    #[cfg(feature = "cache")]
+    fn test_embedded_fallback_cached() {
+        use crate::IconsCache;
+
+        let icons = IconSearch::new_empty().search().icons();
+        let mut cache = IconsCache::from_icons(icons.with_embedded_fallback());
+        let icon = cache.find_icon("folder", 48, 1, "hicolor").unwrap();
+        assert_eq!(
+            icon.bytes(),
+            Some(&include_bytes!("embedded/folder.svg")[..])
+        );
+
+        let dir = test_dir("embedded-fallback-cached");
+        let icons = test_search().add_directories([&dir]).search().icons();
+        let mut cache = IconsCache::from_icons(icons.with_embedded_fallback());
+        let icon = cache
+            .find_icon("text-x-generic", 48, 1, "TestTheme")
+            .unwrap();
+        assert!(icon.bytes().is_some());
+        assert!(cache.find_icon("missing", 48, 1, "TestTheme").is_none());
+    }
+}
@@ -1,15 +1,28 @@
-use crate::{DirectoryIndex, IconSearch, Theme};
-use std::collections::HashMap;
+use crate::profile::ScanCounters;
+use crate::search::{RefreshState, inheritance_chain};
+use crate::{
+    Context, DirectoryIndex, DirectoryType, FileProvider, IconSearch, InheritanceOrder,
+    LookupOutcome, LookupProfile, ScanStats, StdFileProvider, Theme, ThemeInfo, ThemeParseError,
+};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
 use std::fmt::Display;
+use std::fs::Metadata;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 /// Main struct to locate icon files.
 ///
 /// Create this using [`Icons::new`] for the standard configuration, or use [`IconSearch`] if you
 /// wish to tune where icons can be found.
 ///
+/// `Icons` is `Send` and `Sync`, and lookups only need a shared reference, so a single `Icons` can
+/// be shared between threads, for example in a `static OnceLock<Icons>` or behind an [`Arc`].
+///
 /// # Example
 ///
 /// ```rust
@@ -18,10 +31,38 @@ use std::sync::Arc;
 /// Icons::new().find_icon("firefox", 32, 1, "hicolor");
 /// ```
 pub struct Icons {
-    /// Map of "standalone" icons (icons not belonging to any icon theme) to their path.
-    pub standalone_icons: HashMap<String, IconFile>,
+    /// Map of "standalone" icons (icons not belonging to any icon theme) to their files.
+    ///
+    /// One name may have multiple files, for example `app.png` and `app.svg`. They are listed in
+    /// the order they were found in.
+    pub standalone_icons: HashMap<String, Vec<IconFile>>,
+    /// Map of standalone icons in formats outside the Icon Theme specification to their files, see
+    /// [`Icons::find_other_standalone_icon`].
+    pub(crate) other_standalone_icons: HashMap<String, Vec<OtherIconFile>>,
     /// Map of internal theme names to their corresponding [`Theme`]
     pub themes: HashMap<OsString, Arc<Theme>>,
+    /// Called after every lookup, see [`Icons::set_profiler`].
+    pub(crate) profiler: Option<Box<dyn Fn(LookupProfile) + Send + Sync>>,
+    /// Icon names to retry lookups with, see [`Icons::add_alias`].
+    pub(crate) aliases: HashMap<String, String>,
+    /// Icon to return when a lookup fails, see [`Icons::set_fallback_icon`].
+    pub(crate) fallback_icon: Option<String>,
+    /// The theme the `default` theme inherits from, see [`Icons::resolve_default_theme`].
+    pub(crate) default_theme: Option<String>,
+    /// Names of themes that only point at another theme, to the theme they point at. See
+    /// [`Icons::theme`].
+    pub(crate) pointer_themes: HashMap<String, String>,
+    /// Used to access the file system outside of themes, see [`IconSearch::with_file_provider`].
+    pub(crate) file_provider: Arc<dyn FileProvider>,
+    /// File system operations performed to build and refresh this `Icons`.
+    pub(crate) scan_counters: ScanCounters,
+    /// The order the parents of themes were resolved in, see [`IconSearch::inheritance_order`].
+    pub(crate) inheritance_order: InheritanceOrder,
+    /// The search this `Icons` was built from, see [`Icons::refresh_if_changed`].
+    pub(crate) refresh: Option<RefreshState>,
+    /// Whether to fall back to icons embedded in the program, see `Icons::with_embedded_fallback`.
+    #[cfg(feature = "embedded-fallback")]
+    pub(crate) embedded_fallback: bool,
 }
 
 impl Icons {
@@ -33,10 +74,277 @@ impl Icons {
         IconSearch::new().search().icons()
     }
 
+    /// Creates an `Icons` from standalone icons and already resolved themes, for example themes
+    /// created with [`Theme::new`], without searching for them.
+    ///
+    /// Every theme in the `inherits_from` list of a theme must also be in `themes`, under its
+    /// internal name. This is checked in debug builds.
+    /// The file system is accessed through [`StdFileProvider`].
+    pub fn from_parts(
+        standalone_icons: HashMap<String, Vec<IconFile>>,
+        themes: HashMap<OsString, Arc<Theme>>,
+    ) -> Self {
+        debug_assert!(
+            themes.values().all(|theme| {
+                theme.inherits_from.iter().all(|parent| {
+                    themes
+                        .get(&parent.info.internal_name)
+                        .is_some_and(|known| Arc::ptr_eq(known, parent))
+                })
+            }),
+            "all themes that are inherited from must be in `themes`"
+        );
+
+        Icons {
+            standalone_icons,
+            other_standalone_icons: HashMap::new(),
+            themes,
+            profiler: None,
+            aliases: HashMap::new(),
+            fallback_icon: None,
+            default_theme: None,
+            pointer_themes: HashMap::new(),
+            file_provider: Arc::new(StdFileProvider),
+            scan_counters: ScanCounters::default(),
+            inheritance_order: InheritanceOrder::default(),
+            refresh: None,
+            #[cfg(feature = "embedded-fallback")]
+            embedded_fallback: false,
+        }
+    }
+
     /// Access a known icon theme by name
+    ///
+    /// # Pointer themes
+    ///
+    /// Some themes only point at other themes: their index has an `Inherits` key but no
+    /// `Directories`. Most commonly, that is the `default` theme, which many systems use to
+    /// configure the default icon theme. Such themes hold no icons and aren't valid themes, so
+    /// they're not among [`themes`](Icons::themes). Instead, requesting one by name returns the
+    /// first theme it inherits from that was found, as if its name were an alias of that theme.
+    ///
+    /// This applies to all lookups that take a theme name, like [`find_icon`](Icons::find_icon).
     pub fn theme(&self, theme_name: &str) -> Option<Arc<Theme>> {
-        let theme_name: &OsStr = theme_name.as_ref();
-        self.themes.get(theme_name).cloned()
+        self.theme_ref(theme_name).cloned()
+    }
+
+    /// Like [`theme`](Icons::theme), but borrows the theme.
+    fn theme_ref(&self, theme_name: &str) -> Option<&Arc<Theme>> {
+        let theme_name = self.resolve_pointer_theme(theme_name);
+        self.themes.get(OsStr::new(theme_name))
+    }
+
+    /// Returns the theme that `theme_name` points at if it's a pointer theme, or `theme_name`
+    /// itself otherwise. See [`theme`](Icons::theme).
+    fn resolve_pointer_theme<'a>(&'a self, theme_name: &'a str) -> &'a str {
+        self.pointer_themes
+            .get(theme_name)
+            .map_or(theme_name, String::as_str)
+    }
+
+    /// Returns the number of icon themes found.
+    pub fn theme_count(&self) -> usize {
+        self.themes.len()
+    }
+
+    /// Returns `true` if no icon themes and no standalone icons were found.
+    ///
+    /// Lookups on an empty `Icons` always fail, which may happen on minimal systems without any
+    /// icon themes installed. Applications can use this to fall back to bundled icons up-front.
+    pub fn is_empty(&self) -> bool {
+        self.themes.is_empty()
+            && self.standalone_icons.is_empty()
+            && self.other_standalone_icons.is_empty()
+    }
+
+    /// Returns the inheritance graph of all themes: each theme's internal name, paired with the
+    /// internal names of the themes it directly inherits from, in declaration order.
+    ///
+    /// Unlike [`Theme::inherits_from`], which lists all (transitive) parents in lookup order, this
+    /// only lists the parents declared in each theme's [index](crate::ThemeIndex#structfield.inherits).
+    /// Declared parents that weren't found are left out, and `hicolor`, which every theme
+    /// implicitly falls back to, is only listed if declared.
+    ///
+    /// Themes are sorted by internal name.
+    pub fn inheritance_graph(&self) -> Vec<(String, Vec<String>)> {
+        let mut graph = self
+            .themes
+            .iter()
+            .map(|(name, theme)| {
+                let parents = theme
+                    .info
+                    .index
+                    .inherits
+                    .iter()
+                    .filter(|parent| self.themes.contains_key(OsStr::new(parent)))
+                    .cloned()
+                    .collect();
+
+                (name.to_string_lossy().into_owned(), parents)
+            })
+            .collect::<Vec<_>>();
+
+        graph.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        graph
+    }
+
+    /// Returns the internal names of the themes that lookups in the given theme consult, in search
+    /// order: the theme itself, followed by its (transitive) parents, ending with `hicolor` if it
+    /// was found.
+    ///
+    /// Returns `None` if no theme by the given name exists.
+    pub fn theme_chain(&self, theme_name: &str) -> Option<Vec<String>> {
+        let theme = self.theme(theme_name)?;
+
+        let chain = std::iter::once(&theme)
+            .chain(theme.inherits_from.iter())
+            .map(|theme| theme.info.internal_name.to_string_lossy().into_owned())
+            .collect();
+
+        Some(chain)
+    }
+
+    /// Returns the name of the theme that the `default` icon theme inherits from, if there is one.
+    ///
+    /// Some systems configure the default icon theme through a `default` theme (e.g.
+    /// `/usr/share/icons/default/index.theme`) whose `Inherits` key names the actual theme. The
+    /// first theme named there is returned. See [`IconLocations::default_theme`](crate::IconLocations::default_theme).
+    pub fn resolve_default_theme(&self) -> Option<String> {
+        self.default_theme.clone()
+    }
+
+    /// Re-parses the `index.theme` of the theme with the given internal name, and rebuilds it along
+    /// with all themes that inherit from it.
+    ///
+    /// Use this to pick up changes to a single theme, e.g. after it was edited or updated by a
+    /// package manager, without searching for all themes again. The theme is re-read from the same
+    /// directories it was found in. Parents that it newly declares are only used if they were
+    /// already found; to pick up newly installed themes, perform a new [`IconSearch`].
+    ///
+    /// Returns an error, leaving `self` as-is, if no theme by the given name was found, or if its
+    /// index can no longer be read or parsed.
+    pub fn refresh_theme(&mut self, internal_name: &str) -> std::io::Result<()> {
+        let name = OsStr::new(internal_name);
+        let theme = self
+            .themes
+            .get(name)
+            .ok_or_else(|| std::io::Error::other(ThemeParseError::NotAnIconTheme))?;
+
+        let info = ThemeInfo::new_from_folders_counted(
+            name.to_os_string(),
+            theme.info.base_dirs.clone(),
+            &ScanCounters::default(),
+            theme.file_provider.as_ref(),
+        )?;
+        let file_provider = theme.file_provider.clone();
+
+        // a chain lists all transitive parents, so these are all themes whose lookups may pass
+        // through the refreshed theme. Other themes are unaffected. Like during resolving, themes
+        // are rebuilt in order of their names, which decides where inheritance cycles are broken.
+        let mut chains = self
+            .themes
+            .keys()
+            .filter(|other| *other != name)
+            .map(|other| (other.clone(), self.inheritance_chain(other, &info)))
+            .filter(|(_, chain)| chain.iter().any(|parent| parent == name))
+            .collect::<BTreeMap<_, _>>();
+        chains.insert(name.to_os_string(), self.inheritance_chain(name, &info));
+
+        let mut rebuild = Rebuild {
+            themes: &self.themes,
+            chains: &chains,
+            refreshed: &info,
+            file_provider: &file_provider,
+            visited: HashSet::new(),
+            rebuilt: HashMap::new(),
+        };
+        for name in chains.keys() {
+            rebuild.theme(name);
+        }
+
+        let rebuilt = rebuild.rebuilt;
+        self.themes.extend(rebuilt);
+
+        Ok(())
+    }
+
+    /// Computes the lookup order of the parents of the theme called `name`, like
+    /// [`IconLocations::resolve`](crate::IconLocations::resolve) does in the configured
+    /// [`InheritanceOrder`], with `refreshed` replacing
+    /// the info of the theme by the same name.
+    fn inheritance_chain(&self, name: &OsStr, refreshed: &ThemeInfo) -> Vec<OsString> {
+        let parents_of = |node: &OsString| {
+            let inherits = if *node == refreshed.internal_name {
+                &refreshed.index.inherits
+            } else if let Some(theme) = self.themes.get(node) {
+                &theme.info.index.inherits
+            } else {
+                return vec![];
+            };
+
+            inherits
+                .iter()
+                .map(OsString::from)
+                .filter(|parent| self.themes.contains_key(parent))
+                .collect()
+        };
+
+        let hicolor = Some(OsString::from("hicolor")).filter(|h| self.themes.contains_key(h));
+
+        let mut chain = inheritance_chain(
+            name.to_os_string(),
+            parents_of,
+            hicolor,
+            self.inheritance_order,
+        );
+        // the first in the chain is the theme itself
+        chain.remove(0);
+        chain
+    }
+
+    /// Checks whether the directories this `Icons` was built from were modified since, in which
+    /// case themes or standalone icons may have been added or removed.
+    ///
+    /// The modification times of the searched directories, and of the themes' directories and
+    /// `index.theme` files, are compared to those at the time of the search. This is much cheaper
+    /// than a new search, so it can be polled periodically. Each modification time checked is
+    /// counted in [`Icons::scan_stats`].
+    ///
+    /// Icons added to a theme's existing directories don't need a refresh: lookups check the file
+    /// system every time, so they are found right away.
+    ///
+    /// Always returns `false` if this `Icons` wasn't built by an [`IconSearch`], or if the
+    /// [file provider](IconSearch::with_file_provider) doesn't report
+    /// [modification times](FileProvider::modified).
+    pub fn needs_refresh(&self) -> bool {
+        self.refresh
+            .as_ref()
+            .is_some_and(|refresh| refresh.is_outdated(self))
+    }
+
+    /// Repeats the search this `Icons` was built from, with the same directories and options, if
+    /// it [needs a refresh](Icons::needs_refresh). Returns whether it was refreshed.
+    ///
+    /// The [profiler](Icons::set_profiler), [aliases](Icons::add_alias), [fallback
+    /// icon](Icons::set_fallback_icon) and other settings of this `Icons` are kept.
+    pub fn refresh_if_changed(&mut self) -> bool {
+        if !self.needs_refresh() {
+            return false;
+        }
+
+        let refresh = self.refresh.as_ref().expect("checked by `needs_refresh`");
+        let mut icons = refresh.search();
+        icons.profiler = self.profiler.take();
+        icons.aliases = std::mem::take(&mut self.aliases);
+        icons.fallback_icon = self.fallback_icon.take();
+        #[cfg(feature = "embedded-fallback")]
+        {
+            icons.embedded_fallback = self.embedded_fallback;
+        }
+        *self = icons;
+
+        true
     }
 
     /// Like [`find_icon`](self.find_icon), with `theme` being `"hicolor"`, which is the default icon theme.
@@ -47,8 +355,12 @@ impl Icons {
     /// Look up an icon by name, size, scale and theme.
     ///
     /// - If no theme by the given name exists, the `"hicolor"` theme (default theme) is used instead.
+    ///   Names of [pointer themes](Icons::theme), like `"default"`, refer to the
+    ///   theme they point at.
     /// - If the icon is not found in the provided theme, its parents are checked.
     /// - If the icon is not found in any of the themes, the standalone icon list is checked.
+    /// - If that fails too, and an [alias](Icons::add_alias) is registered for the icon, the lookup
+    ///   is repeated with the alias.
     ///
     /// # Icon matching
     ///
@@ -58,6 +370,9 @@ impl Icons {
     /// This will only return `None` if no icon by the specified name exists in the specified theme
     /// and its parents, and no standalone icon by the same name exists either.
     ///
+    /// If a [fallback icon](Icons::set_fallback_icon) is configured, it is looked up in the same
+    /// way and returned instead of `None`. Use [`find_icon_strict`](Icons::find_icon_strict) to
+    /// ignore the fallback icon.
     pub fn find_icon(
         &self,
         icon_name: &str,
@@ -65,258 +380,2988 @@ impl Icons {
         scale: u32,
         theme: &str,
     ) -> Option<IconFile> {
-        if icon_name.is_empty() {
-            return None;
-        }
+        self.find_icon_timed(icon_name, size, scale, theme, true)
+            .map(|(icon, _)| icon)
+    }
 
-        let theme = self.theme(theme).or_else(|| self.theme("hicolor"))?;
-        theme
-            .find_icon(icon_name, size, scale)
-            .or_else(|| self.find_standalone_icon(icon_name))
+    /// Like [`find_icon`](Icons::find_icon), but never returns the
+    /// [fallback icon](Icons::set_fallback_icon): `None` is returned if the icon can't be found.
+    pub fn find_icon_strict(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<IconFile> {
+        self.find_icon_timed(icon_name, size, scale, theme, false)
+            .map(|(icon, _)| icon)
     }
 
-    /// Look up a standalone icon by name.
-    ///
-    /// "Standalone" icons are icons that live outside icon themes, residing at the root in the
-    /// search directories instead.
+    /// Like [`find_icon`](Icons::find_icon), but only returns the path to the icon, for when its
+    /// [file type](IconFile::file_type) doesn't matter.
     ///
-    /// These icons do not have any size or scalability information attached to them.
-    pub fn find_standalone_icon(&self, icon_name: &str) -> Option<IconFile> {
-        self.standalone_icons.get(icon_name).cloned()
+    /// Returns `None` for icons that aren't stored on disk, like the ones embedded with
+    /// `Icons::with_embedded_fallback`.
+    pub fn find_icon_path(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<PathBuf> {
+        self.find_icon(icon_name, size, scale, theme)?
+            .path()
+            .map(Path::to_owned)
     }
 
-    /// Find all icons in all themes, in all of their directories.
+    /// Like [`find_icon`](Icons::find_icon), but checks standalone icons before the theme.
     ///
-    /// Also see [`find_all_icons_filtered`](Icons::find_all_icons_filtered).
-    pub fn find_all_icons(&self) -> impl Iterator<Item = (Arc<Theme>, &DirectoryIndex, IconFile)> {
-        self.find_all_icons_filtered(|_| true, |_| true, |_| true)
+    /// Some (legacy) applications install their real icon as a standalone icon, e.g. in
+    /// `/usr/share/pixmaps`, while themes only provide a generic icon by the same name. Use this
+    /// for such names to prefer the standalone icon. If there is none, this behaves like `find_icon`.
+    pub fn find_standalone_first(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<IconFile> {
+        Some(icon_name)
+            .filter(|icon_name| !icon_name.is_empty())
+            .and_then(|icon_name| self.find_standalone_icon(icon_name))
+            .or_else(|| self.find_icon(icon_name, size, scale, theme))
     }
 
-    /// Find all icons in all themes, in all of their directories, filtered at each stage by a predicate.
-    ///
-    /// This happens lazily: the function returns an iterator that only does the required work
-    /// when you advance it.
-    ///
-    /// <div class="warning">
-    ///
-    /// The output of this function does **not** include standalone icons.
-    /// If you need a full list of icons, use this method and chain it together with the content of
-    /// [`standalone_icons`](Icons#structfield.standalone_icons)
-    ///
-    /// </div>
+    /// Like [`find_icon`](Icons::find_icon), but if the icon isn't in the `primary` theme or its
+    /// parents, the [default theme](Icons::resolve_default_theme) and its parents are checked
+    /// before `"hicolor"`.
     ///
-    /// <div class="warning">
-    ///
-    /// This method is **not** meant for finding icons by name / size; use [`find_icon`](Icons::find_icon) for that.
-    ///
-    /// </div>
-    ///
-    /// # Example
-    ///
-    /// Find all icons belonging to a theme called "Adwaita", in a directory for icons size ≤ 128px:
-    ///
-    /// ```rust
-    /// use icon::{Icons, IconFile};
+    /// This mirrors how desktop environments look up icons for an application that ships or
+    /// prefers its own theme: the application's theme comes first, then the theme the user
+    /// configured, and only then the generic `"hicolor"` icons. Every theme is checked at most
+    /// once, even if it is in both chains.
     ///
-    /// let icons = Icons::new();
-    /// let adwaita: Vec<IconFile> = icons
-    ///     .find_all_icons_filtered(
-    ///         |theme| theme.info.internal_name == "Adwaita",
-    ///         |dir| dir.size <= 128,
-    ///         |_| true,
-    ///     )
-    ///     .map(|(_, _, icon)| icon)
-    ///     .collect();
-    /// ```
-    pub fn find_all_icons_filtered<'a>(
-        &'a self,
-        filter_theme: impl Fn(&Theme) -> bool + 'a,
-        filter_directory: impl Fn(&DirectoryIndex) -> bool + 'a,
-        filter_icon: impl Fn(&IconFile) -> bool + Clone + 'a,
-    ) -> impl Iterator<Item = (Arc<Theme>, &'a DirectoryIndex, IconFile)> {
-        // This function conjures up a big iterator over all icons,
-        // in all themes, in all theme directories. It does that by chaining (with `zip` and `repeat`)
-        // each "level" of the search together:
+    /// If neither `primary` nor the default theme exist, only `"hicolor"` is checked.
+    pub fn find_icon_with_defaults(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        primary: &str,
+    ) -> Option<IconFile> {
+        let hicolor = self.themes.get(OsStr::new("hicolor"));
 
-        // First, find each icon theme, filtered by the `filter_theme` argument:
-        let themes = self
-            .themes
-            .values()
-            .filter(move |theme| filter_theme(theme.as_ref()));
+        let mut themes: Vec<&Arc<Theme>> = Vec::new();
+        let roots = [Some(primary), self.default_theme.as_deref()];
+        for root in roots.into_iter().flatten() {
+            let Some(root) = self.theme_ref(root) else {
+                continue;
+            };
 
-        // Create an iterator that yields each icon theme × icon theme's directories
-        // Item = (&Arc<Theme>, &DirectoryIndex)
-        let dirs = themes
-            .flat_map(|theme| {
-                std::iter::zip(
-                    std::iter::repeat(theme),
-                    theme.info.index.directories.iter(),
-                )
-            })
-            .filter(move |(_, dir)| filter_directory(dir));
+            for theme in std::iter::once(root).chain(root.inherits_from.iter()) {
+                // hicolor is deferred until both chains have been checked
+                let is_hicolor = hicolor.is_some_and(|hicolor| Arc::ptr_eq(hicolor, theme));
+                if !is_hicolor && !themes.iter().any(|seen| Arc::ptr_eq(seen, theme)) {
+                    themes.push(theme);
+                }
+            }
+        }
+        themes.extend(hicolor);
 
-        // Then, for each pair of Theme and DirectoryIndex,
-        // find all files in each suitable directory (which may be multiple, if the theme has many
-        // base directories).
-        // Item = ((&Arc<Theme>, &DirectoryIndex), IconFile)
-        dirs.flat_map(move |(theme, dir)| {
-                // Each "dir" may map to multiple actual fs directories if the theme
-                // has multiple base_dirs.
-                let filter_icon = filter_icon.clone();
-                let dir_file_iterator = theme
-                    .info
-                    .base_dirs
-                    .iter()
-                    .map(|base_dir| base_dir.join(&dir.directory_name))
-                    .flat_map(|dir| dir.read_dir()) // Skip directories we can't read.
-                    .flatten() // Flatten out the dir iterator,
-                    .flatten() // and skip Err entries.
-                    .flat_map(|dir_entry| IconFile::from_path_buf(dir_entry.path())) // And then skip all files that aren't icons.
-                    .filter(move |icon| filter_icon(icon));
+        let find = |icon_name: &str, profile: &mut LookupProfile| {
+            themes
+                .iter()
+                .copied()
+                .find_map(|theme| {
+                    let (dir, icon) =
+                        theme.find_match_here(icon_name, size, scale, &[], profile)?;
+                    Some((icon, Some((theme.as_ref(), dir))))
+                })
+                .or_else(|| self.find_standalone_or_embedded(icon_name, size, scale, None, profile))
+        };
 
-                std::iter::zip(std::iter::repeat((theme, dir)), dir_file_iterator)
-            })
-            // And finally, turn the nested tuple ((a,b), c) into (a, b, c)
-            .map(|((a, b), c)| /*uncurry*/ (a.clone(), b, c))
+        self.profiled(|profile| self.find_icon_with(icon_name, true, profile, find))
+            .map(|(icon, _)| icon)
     }
-}
 
-impl Default for Icons {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+    /// Like [`find_icon`](Icons::find_icon), but if the icon can't be found, falls back to the
+    /// generic icon of its `context`, for example `folder` for a missing icon of a place. See
+    /// [`Context::generic_icons`] for the generic icons of each context.
+    ///
+    /// The [fallback icon](Icons::set_fallback_icon) is only used if the generic icons can't be
+    /// found either.
+    pub fn find_icon_with_context_fallback(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+        context: Context,
+    ) -> Option<IconFile> {
+        if let Some(icon) = self.find_icon_strict(icon_name, size, scale, theme) {
+            return Some(icon);
+        }
 
-/// The path to an icon along with its detected file type.
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct IconFile {
-    /// Absolute path to where this icon is found on disk.
-    path: PathBuf,
-    /// The filetype of the icon, derived from its extension. May be `Png`, `Xpm` or `Svg`.
-    file_type: FileType,
-}
+        let generic_icon = if icon_name.is_empty() {
+            None
+        } else {
+            context
+                .generic_icons(icon_name)
+                .iter()
+                .find_map(|generic| self.find_icon_strict(generic, size, scale, theme))
+        };
 
-impl IconFile {
-    /// Derive the icon name from its path.
-    pub fn icon_name(&self) -> &str {
-        self.path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .expect("protected by type's constructor")
+        generic_icon.or_else(|| {
+            let fallback_icon = self.fallback_icon.as_deref()?;
+            self.find_icon_strict(fallback_icon, size, scale, theme)
+        })
     }
 
-    /// Create an `IconFile` from a filesystem path, deriving its filetype from its extension.
-    pub fn from_path(path: &Path) -> Option<IconFile> {
-        Self::from_path_buf(path.to_owned())
+    /// Look up the icon of an application by its Wayland `app_id` or X11 `WM_CLASS`, as shown in
+    /// taskbars and window switchers.
+    ///
+    /// These identifiers often don't exactly match an icon name, so the following names are
+    /// tried, in order, each in the theme and its parents as well as among the standalone icons:
+    /// 1. the `app_id` as-is, e.g. `org.gnome.Nautilus`;
+    /// 2. the `app_id` in lowercase, e.g. `org.gnome.nautilus`;
+    /// 3. the last component of a reverse-DNS `app_id`, e.g. `Nautilus`;
+    /// 4. that last component in lowercase, e.g. `nautilus`.
+    ///
+    /// Like [`find_icon`](Icons::find_icon), the [fallback icon](Icons::set_fallback_icon) is
+    /// returned if none of these are found.
+    pub fn find_app_icon(
+        &self,
+        app_id: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<IconFile> {
+        let last_component = app_id.rsplit_once('.').map(|(_, last)| last);
+        let candidates = [
+            Some(app_id.to_owned()),
+            Some(app_id.to_lowercase()),
+            last_component.map(str::to_owned),
+            last_component.map(str::to_lowercase),
+        ];
+
+        let mut tried: Vec<String> = Vec::new();
+        for candidate in candidates.into_iter().flatten() {
+            if tried.contains(&candidate) {
+                continue;
+            }
+
+            if let Some(icon) = self.find_icon_strict(&candidate, size, scale, theme) {
+                return Some(icon);
+            }
+            tried.push(candidate);
+        }
+
+        let fallback_icon = self.fallback_icon.as_deref()?;
+        self.find_icon_strict(fallback_icon, size, scale, theme)
     }
 
-    /// Create an `IconFile` from an owned filesystem path, deriving its filetype from its extension.
+    /// Like [`find_icon`](Icons::find_icon), but also returns where the icon was found: in a theme
+    /// (which may be a parent of the requested theme), or among the standalone icons.
     ///
-    /// Returns `None` if the provided path does not have a name or extension valid for icons.
-    pub fn from_path_buf(path_buf: PathBuf) -> Option<IconFile> {
-        // An icon file must have a file stem.
-        path_buf.file_stem()?;
+    /// This is useful for example to style themed and standalone icons differently.
+    pub fn find_icon_source(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<(IconFile, IconSource)> {
+        let (icon, theme) = self.find_icon_timed(icon_name, size, scale, theme, true)?;
 
-        let file_type = FileType::from_path_ext(&path_buf)?;
+        let source = match theme {
+            Some((theme, _)) => {
+                IconSource::Theme(theme.info.internal_name.to_string_lossy().into_owned())
+            }
+            None => IconSource::Standalone,
+        };
 
-        Some(IconFile {
-            path: path_buf,
-            file_type,
-        })
+        Some((icon, source))
     }
 
-    /// Returns the path associated with this icon
-    pub fn path(&self) -> &Path {
-        &self.path
-    }
+    /// Like [`find_icon`](Icons::find_icon), but also returns the
+    /// [context](DirectoryIndex#structfield.context) of the directory the icon was found in, for
+    /// example to check that `folder` resolved to an icon of the `Places` context.
+    ///
+    /// The context is `None` if the directory has none, or for standalone icons. It can be parsed
+    /// into a [`Context`] with [`Context::try_from`].
+    pub fn find_icon_context(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<(IconFile, Option<String>)> {
+        let (icon, found_in) = self.find_icon_timed(icon_name, size, scale, theme, true)?;
+        let context = found_in.and_then(|(_, dir)| dir.context.clone());
 
-    /// Returns this icon's file type
-    pub fn file_type(&self) -> FileType {
-        self.file_type
+        Some((icon, context))
     }
-}
 
-/// Supported image file formats for icons.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub enum FileType {
-    /// `.png` files (Portable Network Graphics)
-    Png,
-    /// `.xpm` files (X PixMap), an image file format used by the X window system.
+    /// Like [`find_icon`](Icons::find_icon), but returns why no icon could be found instead of
+    /// `None`. See [`FindError`].
+    pub fn try_find_icon(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Result<IconFile, FindError> {
+        if icon_name.is_empty() && self.fallback_icon.is_none() {
+            return Err(FindError::EmptyName);
+        }
+
+        // the embedded theme stands in for hicolor, but no theme stands in for a missing one
+        let theme_exists = self.theme_ref(theme).is_some()
+            || (theme == "hicolor" && self.embedded_theme().is_some());
+        if !theme_exists {
+            return Err(FindError::ThemeNotFound);
+        }
+
+        if let Some(icon) = self.find_icon_strict(icon_name, size, scale, theme) {
+            return Ok(icon);
+        }
+
+        let Some(fallback_icon) = &self.fallback_icon else {
+            return Err(FindError::IconNotFound);
+        };
+
+        self.find_icon_strict(fallback_icon, size, scale, theme)
+            .ok_or(FindError::NoFallbackAvailable)
+    }
+
+    /// Like [`find_icon`](Icons::find_icon), but also returns measurements of the work done
+    /// during the lookup, such as the number of themes and directories checked.
+    ///
+    /// Unlike the [profiler](Icons::set_profiler), which observes all lookups, this allows
+    /// inspecting a single lookup, for example to assert on it in tests.
+    pub fn find_icon_with_profile(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> (Option<IconFile>, LookupProfile) {
+        let start = Instant::now();
+        let mut profile = LookupProfile::default();
+        let icon = self.find_icon_profiled(icon_name, size, scale, theme, true, &mut profile);
+        profile.duration = start.elapsed();
+
+        (icon.map(|(icon, _)| icon), profile)
+    }
+
+    fn find_icon_timed(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+        use_fallback: bool,
+    ) -> Option<(IconFile, Option<(&Theme, &DirectoryIndex)>)> {
+        self.profiled(|profile| {
+            self.find_icon_profiled(icon_name, size, scale, theme, use_fallback, profile)
+        })
+    }
+
+    /// Runs a lookup, passing its measurements to the [profiler](Icons::set_profiler), if any.
+    fn profiled<T>(&self, lookup: impl FnOnce(&mut LookupProfile) -> T) -> T {
+        let mut profile = LookupProfile::default();
+        let Some(profiler) = &self.profiler else {
+            return lookup(&mut profile);
+        };
+
+        let start = Instant::now();
+        let result = lookup(&mut profile);
+        profile.duration = start.elapsed();
+
+        profiler(profile);
+
+        result
+    }
+
+    /// The core of [`find_icon`](Icons::find_icon), also returning the theme and directory the
+    /// icon was found in, or `None` for standalone icons.
+    fn find_icon_profiled(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+        use_fallback: bool,
+        profile: &mut LookupProfile,
+    ) -> Option<(IconFile, Option<(&Theme, &DirectoryIndex)>)> {
+        if icon_name.is_empty() && (!use_fallback || self.fallback_icon.is_none()) {
+            return None;
+        }
+
+        let theme = self.lookup_theme(theme)?;
+        let find = |icon_name: &str, profile: &mut LookupProfile| {
+            theme
+                .find_match(icon_name, size, scale, &[], profile)
+                .map(|(theme, dir, icon)| (icon, Some((theme, dir))))
+                .or_else(|| {
+                    self.find_standalone_or_embedded(icon_name, size, scale, Some(theme), profile)
+                })
+        };
+
+        self.find_icon_with(icon_name, use_fallback, profile, find)
+    }
+
+    /// The steps shared by all lookups: looks up `icon_name` with `find`, then its
+    /// [alias](Icons::add_alias), and then the [fallback icon](Icons::set_fallback_icon) if
+    /// `use_fallback` is set. `find` looks up a single name in the themes, followed by
+    /// [the standalone and embedded icons](Icons::find_standalone_or_embedded).
+    fn find_icon_with<'a>(
+        &'a self,
+        icon_name: &str,
+        use_fallback: bool,
+        profile: &mut LookupProfile,
+        mut find: impl FnMut(
+            &str,
+            &mut LookupProfile,
+        ) -> Option<(IconFile, Option<(&'a Theme, &'a DirectoryIndex)>)>,
+    ) -> Option<(IconFile, Option<(&'a Theme, &'a DirectoryIndex)>)> {
+        let fallback_icon = self.fallback_icon.as_deref().filter(|_| use_fallback);
+
+        let icon = if icon_name.is_empty() {
+            None
+        } else {
+            find(icon_name, profile).or_else(|| {
+                let alias = self.aliases.get(icon_name)?;
+                find(alias, profile)
+            })
+        };
+
+        icon.or_else(|| find(fallback_icon?, profile))
+    }
+
+    /// The theme a lookup in `theme` starts at: that theme, or `"hicolor"` if it doesn't exist, or
+    /// the embedded theme (`Icons::with_embedded_fallback`) if neither exist.
+    pub(crate) fn lookup_theme(&self, theme: &str) -> Option<&Theme> {
+        self.theme_ref(theme)
+            .or_else(|| self.themes.get(OsStr::new("hicolor")))
+            .map(Arc::as_ref)
+            .or(self.embedded_theme())
+    }
+
+    /// The part of a lookup that comes after the themes: the standalone icons, and then the
+    /// embedded theme (`Icons::with_embedded_fallback`), unless it was already `searched`.
+    pub(crate) fn find_standalone_or_embedded(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        searched: Option<&Theme>,
+        profile: &mut LookupProfile,
+    ) -> Option<(IconFile, Option<(&'static Theme, &'static DirectoryIndex)>)> {
+        if let Some(icon) = self.find_standalone_icon(icon_name) {
+            profile.outcome = LookupOutcome::Standalone;
+            return Some((icon, None));
+        }
+
+        let embedded_theme = match searched {
+            Some(searched) => self.embedded_theme_after(searched),
+            None => self.embedded_theme(),
+        }?;
+        let (dir, icon) = embedded_theme.find_match_here(icon_name, size, scale, &[], profile)?;
+        Some((icon, Some((embedded_theme, dir))))
+    }
+
+    /// The embedded theme (`Icons::with_embedded_fallback`) to search after `searched` and its
+    /// parents, unless `searched` is the embedded theme itself. It comes last, as if every theme
+    /// inherited from it.
+    fn embedded_theme_after(&self, searched: &Theme) -> Option<&'static Theme> {
+        self.embedded_theme()
+            .filter(|embedded| !std::ptr::eq(*embedded, searched))
+    }
+
+    /// The theme of icons embedded in the program, if enabled with `Icons::with_embedded_fallback`.
+    fn embedded_theme(&self) -> Option<&'static Theme> {
+        #[cfg(feature = "embedded-fallback")]
+        if self.embedded_fallback {
+            return Some(crate::embedded::theme());
+        }
+
+        None
+    }
+
+    /// Set an icon to return from [`find_icon`](Icons::find_icon) when the requested icon can't be
+    /// found, for example `application-x-executable`.
+    ///
+    /// The fallback icon is looked up with the same size, scale and theme as the requested icon.
+    /// Pass `None` to remove the fallback icon.
+    pub fn set_fallback_icon(&mut self, icon_name: Option<&str>) {
+        self.fallback_icon = icon_name.map(ToOwned::to_owned);
+    }
+
+    /// Register an alias: when [`find_icon`](Icons::find_icon) can't find an icon named `from`,
+    /// it retries the lookup with the name `to` instead.
+    ///
+    /// This lets applications use their own icon names, while still picking up icons from the
+    /// theme under their standard names. Aliases are not followed recursively: if the icon `to` can't
+    /// be found either, its own alias isn't tried.
+    ///
+    /// Adding an alias for a name that already has one replaces it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use icon::Icons;
+    ///
+    /// let mut icons = Icons::new();
+    /// icons.add_alias("open-file", "document-open");
+    ///
+    /// icons.find_icon("open-file", 32, 1, "hicolor");
+    /// ```
+    pub fn add_alias(&mut self, from: &str, to: &str) {
+        self.aliases.insert(from.to_owned(), to.to_owned());
+    }
+
+    /// Look up the scalable (vector) version of an icon by name and theme, regardless of size.
+    ///
+    /// See [`Theme::find_scalable_icon`]. Like [`find_icon`](Icons::find_icon), the `"hicolor"`
+    /// theme is used if no theme by the given name exists, and standalone icons and then the
+    /// embedded theme (`Icons::with_embedded_fallback`) are checked if none of the themes have a
+    /// scalable version of the icon.
+    pub fn find_scalable_icon(&self, icon_name: &str, theme: &str) -> Option<IconFile> {
+        if icon_name.is_empty() {
+            return None;
+        }
+
+        let theme = self.lookup_theme(theme)?;
+        theme
+            .find_scalable_icon(icon_name)
+            .or_else(|| self.find_standalone_icon_typed(icon_name, &[FileType::Svg]))
+            .or_else(|| {
+                self.embedded_theme_after(theme)?
+                    .find_scalable_icon(icon_name)
+            })
+    }
+
+    /// Like [`find_icon`](Icons::find_icon), but if no icon is found, also looks for it in the
+    /// `extra_dirs`.
+    ///
+    /// The extra directories are treated like base directories only containing standalone icons:
+    /// any icon themes in them are ignored. They are searched in order, after the standalone icons
+    /// of this `Icons`.
+    ///
+    /// This is useful for icon sources that come and go, like plugins, where rebuilding `Icons`
+    /// for every change would be too expensive.
+    ///
+    /// The [fallback icon](Icons::set_fallback_icon) is only used if the icon isn't found in the
+    /// extra directories either.
+    pub fn find_icon_with_extra_dirs(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+        extra_dirs: &[&Path],
+    ) -> Option<IconFile> {
+        let icon = if icon_name.is_empty() {
+            None
+        } else {
+            self.find_icon_strict(icon_name, size, scale, theme)
+                .or_else(|| {
+                    let file_names = Theme::possible_file_names_for(icon_name);
+
+                    extra_dirs
+                        .iter()
+                        .flat_map(|dir| file_names.iter().map(|file_name| dir.join(file_name)))
+                        .find(|path| self.file_provider.exists(path))
+                        .and_then(IconFile::from_path_buf)
+                })
+        };
+
+        icon.or_else(|| {
+            let fallback_icon = self.fallback_icon.as_deref()?;
+            self.find_icon_strict(fallback_icon, size, scale, theme)
+        })
+    }
+
+    /// Returns the number of file system operations that were performed while searching for icons
+    /// and resolving themes to create this `Icons`, and while checking whether it [needs a
+    /// refresh](Icons::needs_refresh).
+    ///
+    /// This is useful to find out why creating an `Icons` is slow, for example on slow storage.
+    pub fn scan_stats(&self) -> ScanStats {
+        self.scan_counters.snapshot()
+    }
+
+    /// Install a profiler, which is called with measurements of each lookup performed with
+    /// [`find_icon`](Icons::find_icon), or the lookups built on it, after it finishes.
+    ///
+    /// This replaces any previously installed profiler.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use icon::Icons;
+    ///
+    /// let mut icons = Icons::new();
+    /// icons.set_profiler(|profile| {
+    ///     println!("lookup took {:?} and {} syscalls", profile.duration, profile.files_checked)
+    /// });
+    ///
+    /// icons.find_icon("firefox", 32, 1, "hicolor");
+    /// ```
+    pub fn set_profiler(&mut self, profiler: impl Fn(LookupProfile) + Send + Sync + 'static) {
+        self.profiler = Some(Box::new(profiler));
+    }
+
+    /// Remove the profiler installed with [`set_profiler`](Icons::set_profiler), if any.
+    pub fn clear_profiler(&mut self) {
+        self.profiler = None;
+    }
+
+    /// Like [`find_icon`](Icons::find_icon), but demotes raster icons found in scalable directories.
+    ///
+    /// See [`Theme::find_icon_checked`] for details.
+    pub fn find_icon_checked(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<IconFile> {
+        if icon_name.is_empty() {
+            return None;
+        }
+
+        let theme = self.lookup_theme(theme)?;
+        theme.find_icon_checked(icon_name, size, scale).or_else(|| {
+            let mut profile = LookupProfile::default();
+            self.find_standalone_or_embedded(icon_name, size, scale, Some(theme), &mut profile)
+                .map(|(icon, _)| icon)
+        })
+    }
+
+    /// Like [`find_icon`](Icons::find_icon), but among exact matches, prefers the icon of the
+    /// theme's first base directory, in the order of the search directories. See
+    /// [`Theme::find_icon_here_base_dir_first`].
+    ///
+    /// [Aliases](Icons::add_alias) and the [fallback icon](Icons::set_fallback_icon) are not used.
+    pub fn find_icon_base_dir_first(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<IconFile> {
+        if icon_name.is_empty() {
+            return None;
+        }
+
+        let theme = self.lookup_theme(theme)?;
+        self.profiled(|profile| {
+            theme
+                .find_match_base_dir_first(icon_name, size, scale, profile)
+                .map(|(_, _, icon)| icon)
+                .or_else(|| {
+                    self.find_standalone_or_embedded(icon_name, size, scale, Some(theme), profile)
+                        .map(|(icon, _)| icon)
+                })
+        })
+    }
+
+    /// Like [`find_icon`](Icons::find_icon), but only returns an icon that matches the size and
+    /// scale exactly, never the closest match. See [`Theme::find_icon_exact`].
+    ///
+    /// Standalone icons have no known size, so they are not considered.
+    /// [Aliases](Icons::add_alias) and the [fallback icon](Icons::set_fallback_icon) are not used
+    /// either.
+    pub fn find_icon_exact(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<IconFile> {
+        if icon_name.is_empty() {
+            return None;
+        }
+
+        let theme = self.lookup_theme(theme)?;
+        theme.find_icon_exact(icon_name, size, scale).or_else(|| {
+            self.embedded_theme_after(theme)?
+                .find_icon_exact(icon_name, size, scale)
+        })
+    }
+
+    /// Like [`find_icon`](Icons::find_icon), but never upscales icons if a larger icon exists,
+    /// for pixel-perfect rendering. See [`Theme::find_icon_no_upscale`].
+    ///
+    /// [Aliases](Icons::add_alias) and the [fallback icon](Icons::set_fallback_icon) are not used.
+    pub fn find_icon_no_upscale(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+    ) -> Option<IconFile> {
+        if icon_name.is_empty() {
+            return None;
+        }
+
+        let theme = self.lookup_theme(theme)?;
+        theme
+            .find_icon_no_upscale(icon_name, size, scale)
+            .or_else(|| self.find_standalone_icon(icon_name))
+            .or_else(|| {
+                self.embedded_theme_after(theme)?
+                    .find_icon_no_upscale(icon_name, size, scale)
+            })
+    }
+
+    /// Like [`find_icon`](Icons::find_icon), but for a display with a possibly fractional scale
+    /// factor, like 1.5.
+    ///
+    /// Icon theme directories only have integer scales, so `display_scale` is rounded to the
+    /// closest scale available in the theme (or its parents), preferring the larger scale if two
+    /// are equally close, as downscaling icons looks better than upscaling them. The icon is then
+    /// looked up at that scale.
+    pub fn find_icon_for_scale(
+        &self,
+        icon_name: &str,
+        logical_size: u32,
+        display_scale: f32,
+        theme: &str,
+    ) -> Option<IconFile> {
+        let scale = self.nearest_scale(display_scale, theme);
+
+        self.find_icon(icon_name, logical_size, scale, theme)
+    }
+
+    fn nearest_scale(&self, display_scale: f32, theme: &str) -> u32 {
+        // NaN and non-positive scales make no sense, ignore them
+        let display_scale = if display_scale > 0.0 {
+            display_scale
+        } else {
+            1.0
+        };
+
+        let Some(theme) = self.lookup_theme(theme) else {
+            return display_scale.round().max(1.0) as u32;
+        };
+
+        let mut scales = std::iter::once(theme)
+            .chain(theme.inherits_from.iter().map(Arc::as_ref))
+            .flat_map(|theme| theme.info.index.directories.iter().map(|dir| dir.scale))
+            .collect::<Vec<_>>();
+        scales.sort_unstable();
+        scales.dedup();
+
+        scales
+            .into_iter()
+            // `min_by` keeps the first minimum, so iterate from large to small scales
+            .rev()
+            .min_by(|a, b| {
+                let a = (*a as f32 - display_scale).abs();
+                let b = (*b as f32 - display_scale).abs();
+                a.total_cmp(&b)
+            })
+            .unwrap_or_else(|| display_scale.round().max(1.0) as u32)
+    }
+
+    /// Like [`find_icon`](Icons::find_icon), but only returns an icon if its
+    /// [size distance](DirectoryIndex::size_distance) to the requested size and scale is at most
+    /// `max_distance`. Exact matches have a distance of 0, so they always qualify.
+    ///
+    /// If the best match in a theme is too far off, its parents, and then the
+    /// embedded theme (`Icons::with_embedded_fallback`), are checked for a closer match.
+    /// Standalone icons have no size information, so they are never returned.
+    pub fn find_icon_within(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+        max_distance: u32,
+    ) -> Option<IconFile> {
+        if icon_name.is_empty() {
+            return None;
+        }
+
+        let theme = self.lookup_theme(theme)?;
+
+        std::iter::once(theme)
+            .chain(theme.inherits_from.iter().map(Arc::as_ref))
+            .chain(self.embedded_theme_after(theme))
+            .find_map(|theme| {
+                let (dir, icon) = theme.find_match_here(
+                    icon_name,
+                    size,
+                    scale,
+                    &[],
+                    &mut LookupProfile::default(),
+                )?;
+
+                (dir.size_distance(size, scale) <= max_distance).then_some(icon)
+            })
+    }
+
+    /// Like [`find_icon`](Icons::find_icon), but gives up once `deadline` has passed, returning
+    /// `None`.
+    ///
+    /// This bounds the time spent on lookups that have to check many directories, which may be
+    /// slow on network file systems, for example to keep a user interface responsive.
+    ///
+    /// <div class="warning">
+    ///
+    /// The deadline is only checked before each directory is probed. A single file system call
+    /// that blocks is not interrupted, so the lookup may still overrun the deadline.
+    ///
+    /// </div>
+    pub fn find_icon_deadline(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+        deadline: Instant,
+    ) -> Option<IconFile> {
+        let theme = self.lookup_theme(theme)?;
+        let find = |icon_name: &str, profile: &mut LookupProfile| {
+            let icon = theme
+                .find_match_until(icon_name, size, scale, &[], Some(deadline), profile)
+                .map(|(theme, dir, icon)| (icon, Some((theme, dir))));
+
+            if icon.is_some() || Instant::now() >= deadline {
+                // the themes may have given up before reaching the icon, in which case a
+                // standalone icon by the same name isn't the right match
+                return icon;
+            }
+
+            self.find_standalone_or_embedded(icon_name, size, scale, Some(theme), profile)
+        };
+
+        self.profiled(|profile| self.find_icon_with(icon_name, true, profile, find))
+            .map(|(icon, _)| icon)
+    }
+
+    /// Find the best match for an icon in every theme that has it, sorted from best to worst match.
+    ///
+    /// Each entry holds the theme's internal name, the icon found, and its size distance to the
+    /// requested size and scale (see [`DirectoryIndex::size_distance`]).
+    /// Only icons in the themes themselves are considered: parents are not searched, so that every
+    /// theme's own version of the icon is returned. Standalone icons are not included.
+    pub fn find_icon_all_themes(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+    ) -> Vec<(String, IconFile, u32)> {
+        if icon_name.is_empty() {
+            return vec![];
+        }
+
+        let mut matches = self
+            .themes
+            .iter()
+            .filter_map(|(name, theme)| {
+                let (dir, icon) = theme.find_match_here(
+                    icon_name,
+                    size,
+                    scale,
+                    &[],
+                    &mut LookupProfile::default(),
+                )?;
+
+                Some((
+                    name.to_string_lossy().into_owned(),
+                    icon,
+                    dir.size_distance(size, scale),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        // sort by name as well, so that the order doesn't depend on that of the `themes` map
+        matches.sort_by(|(name_a, _, distance_a), (name_b, _, distance_b)| {
+            distance_a.cmp(distance_b).then_with(|| name_a.cmp(name_b))
+        });
+
+        matches
+    }
+
+    /// Find the best version of an icon in any theme, for when it doesn't matter which theme the
+    /// icon comes from.
+    ///
+    /// Themes are preferred in this order: the [default theme](Icons::resolve_default_theme) and
+    /// its parents, `"hicolor"`, and then all other themes, by internal name. An icon that matches
+    /// the size and scale exactly is returned from the first theme that has one. Otherwise, the
+    /// icon closest in size across all themes is returned, preferring earlier themes on ties.
+    ///
+    /// If no theme has the icon, standalone icons, [aliases](Icons::add_alias) and the
+    /// [fallback icon](Icons::set_fallback_icon) are used like in [`find_icon`](Icons::find_icon).
+    pub fn best_icon(&self, icon_name: &str, size: u32, scale: u32) -> Option<IconFile> {
+        let mut themes: Vec<&Arc<Theme>> = Vec::new();
+        let default_chain = self
+            .default_theme
+            .as_deref()
+            .and_then(|name| self.themes.get(OsStr::new(name)))
+            .into_iter()
+            .flat_map(|theme| std::iter::once(theme).chain(theme.inherits_from.iter()));
+        let hicolor = self.themes.get(OsStr::new("hicolor"));
+        let mut others = self.themes.iter().collect::<Vec<_>>();
+        others.sort_by_key(|(name, _)| *name);
+
+        let candidates = default_chain
+            .chain(hicolor)
+            .chain(others.into_iter().map(|(_, theme)| theme));
+        for theme in candidates {
+            if !themes.iter().any(|seen| Arc::ptr_eq(seen, theme)) {
+                themes.push(theme);
+            }
+        }
+
+        let find = |icon_name: &str, profile: &mut LookupProfile| {
+            let mut best: Option<(u32, IconFile, &Theme, &DirectoryIndex)> = None;
+            for &theme in &themes {
+                let Some((dir, icon)) = theme.find_match_here(icon_name, size, scale, &[], profile)
+                else {
+                    continue;
+                };
+
+                let distance = dir.size_distance(size, scale);
+                if distance == 0 {
+                    return Some((icon, Some((theme.as_ref(), dir))));
+                }
+                // only replace on strictly closer matches, so that earlier themes win ties
+                if best.as_ref().is_none_or(|(best, ..)| distance < *best) {
+                    best = Some((distance, icon, theme, dir));
+                }
+            }
+
+            best.map(|(_, icon, theme, dir)| (icon, Some((theme, dir))))
+                .or_else(|| self.find_standalone_or_embedded(icon_name, size, scale, None, profile))
+        };
+
+        self.profiled(|profile| self.find_icon_with(icon_name, true, profile, find))
+            .map(|(icon, _)| icon)
+    }
+
+    /// Find all icons of a theme (but not its parents) whose actual dimensions don't match the
+    /// size of the directory they're in, for example to validate a theme before publishing it.
+    ///
+    /// Only the headers of the icons are read, see [`IconFile::dimensions`]. Icons are expected to
+    /// be square, and as wide as their directory's size multiplied by its scale. Icons in
+    /// [Scalable](DirectoryType::Scalable) directories can be used at many sizes, and icons whose
+    /// dimensions can't be read (like SVGs) are skipped.
+    ///
+    /// The mismatches are returned in the order of the theme's directories, and then by icon name.
+    /// Returns an empty list if no theme by the given name exists.
+    pub fn validate_sizes(&self, theme: &str) -> Vec<SizeMismatch> {
+        let Some(theme) = self.theme(theme) else {
+            return vec![];
+        };
+
+        let directories = theme
+            .info
+            .index
+            .directories
+            .iter()
+            .filter(|dir| dir.directory_type != DirectoryType::Scalable);
+
+        let mut mismatches = vec![];
+        for directory in directories {
+            let expected = directory.size * directory.scale;
+
+            let mut icons = theme.icons_in_directory(directory).collect::<Vec<_>>();
+            icons.sort_by(|a, b| (a.icon_name(), a.path()).cmp(&(b.icon_name(), b.path())));
+
+            mismatches.extend(icons.into_iter().filter_map(|icon| {
+                let actual = icon.dimensions()?;
+                (actual != (expected, expected)).then(|| SizeMismatch {
+                    icon,
+                    directory_name: directory.directory_name.clone(),
+                    expected,
+                    actual,
+                })
+            }));
+        }
+
+        mismatches
+    }
+
+    /// Checks whether an icon by the given name exists at any size, in the theme or its parents,
+    /// as a standalone icon, or in the embedded theme (`Icons::with_embedded_fallback`), under its
+    /// own name or its [alias](Icons::add_alias). These are the icons [`find_icon`](Icons::find_icon)
+    /// returns; the [fallback icon](Icons::set_fallback_icon) and
+    /// [other standalone icons](Icons::find_other_standalone_icon) don't count.
+    ///
+    /// Like [`find_icon`](Icons::find_icon), the `"hicolor"` theme is used if no theme by the given
+    /// name exists.
+    pub fn has_icon(&self, icon_name: &str, theme: &str) -> bool {
+        if icon_name.is_empty() {
+            return false;
+        }
+
+        let Some(theme) = self.lookup_theme(theme) else {
+            return false;
+        };
+
+        let exists = |icon_name: &str| {
+            self.standalone_icons.contains_key(icon_name)
+                || std::iter::once(theme)
+                    .chain(theme.inherits_from.iter().map(Arc::as_ref))
+                    .chain(self.embedded_theme_after(theme))
+                    .any(|theme| theme.find_icon_files(icon_name).next().is_some())
+        };
+
+        exists(icon_name)
+            || self
+                .aliases
+                .get(icon_name)
+                .is_some_and(|alias| exists(alias))
+    }
+
+    /// Returns the subset of `candidates` that [`find_icon`](Icons::find_icon) finds an icon for in
+    /// the given theme, without the fallback icon. See [`has_icon`](Icons::has_icon) for which
+    /// icons count.
+    ///
+    /// The order of `candidates` is preserved.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use icon::Icons;
+    ///
+    /// let icons = Icons::new();
+    /// let present = icons.present_names(["firefox", "thunderbird", "gimp"], "hicolor");
+    ///
+    /// println!("icons available for: {present:?}");
+    /// ```
+    pub fn present_names<'a>(
+        &self,
+        candidates: impl IntoIterator<Item = &'a str>,
+        theme: &str,
+    ) -> Vec<&'a str> {
+        candidates
+            .into_iter()
+            .filter(|name| self.has_icon(name, theme))
+            .collect()
+    }
+
+    /// Compares which of the given icon names two themes can provide, for example to find out which
+    /// icons would go missing when switching from `theme_a` to `theme_b`.
+    ///
+    /// Each name is checked with [`has_icon`](Icons::has_icon), so an icon counts as provided if
+    /// the theme or one of its parents has it at any size. Standalone icons are available to both
+    /// themes, so names only found as standalone icons never end up in the difference.
+    /// The order of `names` is preserved in each list of the result.
+    pub fn coverage_diff(&self, names: &[&str], theme_a: &str, theme_b: &str) -> CoverageDiff {
+        let mut diff = CoverageDiff::default();
+
+        for &name in names {
+            let list = match (self.has_icon(name, theme_a), self.has_icon(name, theme_b)) {
+                (true, true) => continue,
+                (true, false) => &mut diff.only_in_a,
+                (false, true) => &mut diff.only_in_b,
+                (false, false) => &mut diff.in_neither,
+            };
+
+            list.push(name.to_owned());
+        }
+
+        diff
+    }
+
+    /// Returns the internal names of all themes that provide an icon by the given name themselves,
+    /// at any size. Icons the themes would only find through their parents are not counted.
+    ///
+    /// This can be used to find out which installed themes have the icons an application needs,
+    /// for example to recommend a theme. Themes are listed in order of their internal name; themes
+    /// whose internal name isn't valid UTF-8 are skipped.
+    pub fn themes_providing(&self, icon_name: &str) -> Vec<&str> {
+        let mut themes = self
+            .themes
+            .iter()
+            .filter(|(_, theme)| theme.find_icon_files(icon_name).next().is_some())
+            .filter_map(|(name, _)| name.to_str())
+            .collect::<Vec<_>>();
+
+        themes.sort_unstable();
+
+        themes
+    }
+
+    /// Find all icons whose name matches a glob `pattern`, in the theme and its parents, and then
+    /// the embedded theme (`Icons::with_embedded_fallback`), for example to export all `folder-*`
+    /// icons.
+    ///
+    /// The pattern matches whole icon names, where `*` matches any number of characters and `?`
+    /// matches a single character. Like [`find_icon`](Icons::find_icon), the `"hicolor"` theme is
+    /// used if no theme by the given name exists.
+    ///
+    /// Every file of a matching icon is returned, at all sizes, paired with the icon's name. Only
+    /// the files of the first theme in the chain that has an icon are returned, as those are the
+    /// ones lookups use; to pick the best size for an icon, pass its name to `find_icon`.
+    /// Icons are ordered by name. Standalone icons are not included.
+    pub fn find_icons_matching(
+        &self,
+        pattern: &str,
+        theme: &str,
+    ) -> impl Iterator<Item = (String, IconFile)> {
+        let theme = self.lookup_theme(theme);
+        let chain = theme.iter().flat_map(|&theme| {
+            std::iter::once(theme)
+                .chain(theme.inherits_from.iter().map(Arc::as_ref))
+                .chain(self.embedded_theme_after(theme))
+        });
+
+        let mut matches: Vec<(String, IconFile)> = Vec::new();
+        for theme in chain {
+            let found_before = matches.len();
+
+            let icons = theme
+                .info
+                .index
+                .directories
+                .iter()
+                .flat_map(|dir| {
+                    theme
+                        .info
+                        .base_dirs
+                        .iter()
+                        .map(|base_dir| base_dir.join(&dir.directory_name))
+                })
+                .flat_map(|dir| theme.file_provider.read_dir(&dir))
+                .flatten()
+                .filter(|entry| !entry.is_dir)
+                .flat_map(|entry| {
+                    let data = theme.file_provider.embedded_contents(&entry.path);
+                    IconFile::from_path_buf(entry.path).map(|icon| icon.with_embedded_data(data))
+                })
+                .filter(|icon| glob_matches(pattern, icon.icon_name()));
+
+            for icon in icons {
+                // names provided by an earlier theme in the chain shadow this theme's icons
+                let name = icon.icon_name();
+                if !matches[..found_before]
+                    .iter()
+                    .any(|(found, _)| found == name)
+                {
+                    matches.push((name.to_owned(), icon));
+                }
+            }
+        }
+
+        matches.sort_by(|(name_a, icon_a), (name_b, icon_b)| {
+            name_a
+                .cmp(name_b)
+                .then_with(|| icon_a.path().cmp(&icon_b.path()))
+        });
+
+        matches.into_iter()
+    }
+
+    /// Look up a standalone icon by name.
+    ///
+    /// "Standalone" icons are icons that live outside icon themes, residing at the root in the
+    /// search directories instead.
+    ///
+    /// These icons do not have any size or scalability information attached to them.
+    ///
+    /// If there are multiple files for the icon, they are preferred in the order of
+    /// [`FileType::types`]. Use [`find_standalone_icon_typed`](Icons::find_standalone_icon_typed)
+    /// to pick another order.
+    pub fn find_standalone_icon(&self, icon_name: &str) -> Option<IconFile> {
+        self.find_standalone_icon_typed(icon_name, &[])
+    }
+
+    /// Like [`find_standalone_icon`](Icons::find_standalone_icon), but only considers icon files
+    /// of the given types, preferring earlier types over later ones.
+    ///
+    /// An empty `accept` list accepts all types, in the order of [`FileType::types`].
+    pub fn find_standalone_icon_typed(
+        &self,
+        icon_name: &str,
+        accept: &[FileType],
+    ) -> Option<IconFile> {
+        let files = self.standalone_icons.get(icon_name)?;
+        let all_types = FileType::types();
+        let accept = if accept.is_empty() {
+            &all_types
+        } else {
+            accept
+        };
+
+        accept
+            .iter()
+            .find_map(|file_type| files.iter().find(|icon| icon.file_type() == *file_type))
+            .cloned()
+    }
+
+    /// Looks up a standalone icon in a format outside the Icon Theme specification, like `.ico` or
+    /// `.gif`. These are only found when opted into with [`IconSearch::standalone_extensions`],
+    /// and are never returned by [`find_standalone_icon`](Icons::find_standalone_icon).
+    ///
+    /// If there are multiple files for the icon, they are preferred in the order their extensions
+    /// were passed to [`IconSearch::standalone_extensions`].
+    pub fn find_other_standalone_icon(&self, icon_name: &str) -> Option<&OtherIconFile> {
+        self.other_standalone_icons.get(icon_name)?.first()
+    }
+
+    /// Iterate over the names of all standalone icons, in no particular order.
+    ///
+    /// See [`find_standalone_icon`](Icons::find_standalone_icon).
+    pub fn standalone_icon_names(&self) -> impl Iterator<Item = &str> {
+        self.standalone_icons.keys().map(String::as_str)
+    }
+
+    /// Iterate over all standalone icons, in no particular order.
+    ///
+    /// If an icon exists in several formats or locations, all of its files are included.
+    /// This can be chained with [`find_all_icons`](Icons::find_all_icons) for a full list of icons.
+    pub fn standalone_icons_iter(&self) -> impl Iterator<Item = &IconFile> {
+        self.standalone_icons.values().flatten()
+    }
+
+    /// Iterate over every directory of every theme, paired with the theme it belongs to.
+    ///
+    /// This only uses the themes' indices, and does not access the file system: directories
+    /// are listed even if they don't exist on disk (see [`Theme::directory_exists`]).
+    pub fn all_directories(&self) -> impl Iterator<Item = (&Arc<Theme>, &DirectoryIndex)> {
+        self.themes.values().flat_map(|theme| {
+            theme
+                .info
+                .index
+                .directories
+                .iter()
+                .map(move |dir| (theme, dir))
+        })
+    }
+
+    /// Find all icons in all themes, in all of their directories.
+    ///
+    /// Also see [`find_all_icons_filtered`](Icons::find_all_icons_filtered).
+    pub fn find_all_icons(&self) -> AllIcons<'_> {
+        self.find_all_icons_filtered(|_| true, |_| true, |_| true)
+    }
+
+    /// Estimate how many icons [`find_all_icons`](Icons::find_all_icons) would return, for
+    /// example to preallocate a buffer for them.
+    ///
+    /// This is only an approximation: it counts all entries of every theme directory, without
+    /// checking whether they are icons. As directories usually only contain icons, it is an upper
+    /// bound that is close to the real count. Like `find_all_icons`, standalone icons are not
+    /// included; see [`standalone_icons_iter`](Icons::standalone_icons_iter) for those.
+    pub fn estimate_icon_count(&self) -> usize {
+        self.all_directories()
+            .flat_map(|(theme, dir)| {
+                theme
+                    .info
+                    .base_dirs
+                    .iter()
+                    .map(|base_dir| base_dir.join(&dir.directory_name))
+                    .flat_map(|dir| theme.file_provider.read_dir(&dir))
+                    .map(|entries| entries.len())
+            })
+            .sum()
+    }
+
+    /// Like [`find_all_icons`](Icons::find_all_icons), but also includes standalone icons.
+    ///
+    /// Icons belonging to a theme come first, followed by all standalone icons.
+    /// For standalone icons, the theme and directory are `None`.
+    pub fn find_all_icons_including_standalone(
+        &self,
+    ) -> impl Iterator<Item = (Option<Arc<Theme>>, Option<&DirectoryIndex>, IconFile)> {
+        let themed = self
+            .find_all_icons()
+            .map(|(theme, dir, icon)| (Some(theme), Some(dir), icon));
+        let standalone = self
+            .standalone_icons_iter()
+            .map(|icon| (None, None, icon.clone()));
+
+        themed.chain(standalone)
+    }
+
+    /// Find all icons in all themes, in all of their directories, filtered at each stage by a predicate.
+    ///
+    /// This happens lazily: the function returns an iterator that only does the required work
+    /// when you advance it.
+    ///
+    /// <div class="warning">
+    ///
+    /// The output of this function does **not** include standalone icons.
+    /// If you need a full list of icons, use this method and chain it together with
+    /// [`standalone_icons_iter`](Icons::standalone_icons_iter), or use
+    /// [`find_all_icons_including_standalone`](Icons::find_all_icons_including_standalone).
+    ///
+    /// </div>
+    ///
+    /// <div class="warning">
+    ///
+    /// This method is **not** meant for finding icons by name / size; use [`find_icon`](Icons::find_icon) for that.
+    ///
+    /// </div>
+    ///
+    /// # Example
+    ///
+    /// Find all icons belonging to a theme called "Adwaita", in a directory for icons size ≤ 128px:
+    ///
+    /// ```rust
+    /// use icon::{Icons, IconFile};
+    ///
+    /// let icons = Icons::new();
+    /// let adwaita: Vec<IconFile> = icons
+    ///     .find_all_icons_filtered(
+    ///         |theme| theme.info.internal_name == "Adwaita",
+    ///         |dir| dir.size <= 128,
+    ///         |_| true,
+    ///     )
+    ///     .map(|(_, _, icon)| icon)
+    ///     .collect();
+    /// ```
+    pub fn find_all_icons_filtered<'a>(
+        &'a self,
+        filter_theme: impl Fn(&Theme) -> bool + 'a,
+        filter_directory: impl Fn(&DirectoryIndex) -> bool + 'a,
+        filter_icon: impl Fn(&IconFile) -> bool + Clone + 'a,
+    ) -> AllIcons<'a> {
+        // This function conjures up a big iterator over all icons,
+        // in all themes, in all theme directories. It does that by chaining (with `zip` and `repeat`)
+        // each "level" of the search together:
+
+        // First, find each icon theme, filtered by the `filter_theme` argument:
+        let themes = self
+            .themes
+            .values()
+            .filter(move |theme| filter_theme(theme.as_ref()));
+
+        // Create an iterator that yields each icon theme × icon theme's directories
+        // Item = (&Arc<Theme>, &DirectoryIndex)
+        let dirs = themes
+            .flat_map(|theme| {
+                std::iter::zip(
+                    std::iter::repeat(theme),
+                    theme.info.index.directories.iter(),
+                )
+            })
+            .filter(move |(_, dir)| filter_directory(dir));
+
+        // Then, for each pair of Theme and DirectoryIndex,
+        // find all files in each suitable directory (which may be multiple, if the theme has many
+        // base directories).
+        // Item = ((&Arc<Theme>, &DirectoryIndex), IconFile)
+        let icons = dirs
+            .flat_map(move |(theme, dir)| {
+                // Each "dir" may map to multiple actual fs directories if the theme
+                // has multiple base_dirs.
+                let filter_icon = filter_icon.clone();
+                let dir_file_iterator = theme
+                    .info
+                    .base_dirs
+                    .iter()
+                    .map(|base_dir| base_dir.join(&dir.directory_name))
+                    .flat_map(|dir| theme.file_provider.read_dir(&dir)) // Skip directories we can't read.
+                    .flatten() // Flatten out the entries,
+                    .flat_map(|dir_entry| IconFile::from_path_buf(dir_entry.path)) // and then skip all files that aren't icons.
+                    .filter(move |icon| filter_icon(icon));
+
+                std::iter::zip(std::iter::repeat((theme, dir)), dir_file_iterator)
+            })
+            // And finally, turn the nested tuple ((a,b), c) into (a, b, c)
+            .map(|((a, b), c)| /*uncurry*/ (a.clone(), b, c));
+
+        AllIcons {
+            inner: Box::new(icons),
+        }
+    }
+}
+
+/// Checks whether `name` matches the glob `pattern` as a whole, where `*` matches any number of
+/// characters and `?` matches exactly one.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    let (mut p, mut n) = (0, 0);
+    // the position of the last `*` in the pattern, and of the name when it was reached
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => {
+                // let the last `*` match one more character, if there is one
+                let Some((star, matched)) = backtrack else {
+                    return false;
+                };
+                backtrack = Some((star, matched + 1));
+                p = star + 1;
+                n = matched + 1;
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Rebuilds the themes affected by [`Icons::refresh_theme`], parents first.
+struct Rebuild<'a> {
+    themes: &'a HashMap<OsString, Arc<Theme>>,
+    /// The themes to rebuild, and the lookup order of their parents.
+    chains: &'a BTreeMap<OsString, Vec<OsString>>,
+    /// The new info of the refreshed theme.
+    refreshed: &'a ThemeInfo,
+    file_provider: &'a Arc<dyn FileProvider>,
+    /// The themes that were rebuilt, or are being rebuilt.
+    visited: HashSet<OsString>,
+    rebuilt: HashMap<OsString, Arc<Theme>>,
+}
+
+impl Rebuild<'_> {
+    fn theme(&mut self, name: &OsString) -> Option<Arc<Theme>> {
+        if let Some(theme) = self.rebuilt.get(name) {
+            return Some(theme.clone());
+        }
+
+        let Some(chain) = self.chains.get(name) else {
+            // unaffected by the refresh
+            return self.themes.get(name).cloned();
+        };
+
+        // a theme that was visited but not rebuilt yet is being rebuilt already, meaning themes
+        // inherit from each other in a cycle. the cycle is broken here.
+        if !self.visited.insert(name.clone()) {
+            return None;
+        }
+
+        let inherits_from = chain
+            .iter()
+            .filter_map(|parent| self.theme(parent))
+            .collect();
+        let info = if *name == self.refreshed.internal_name {
+            self.refreshed.clone()
+        } else {
+            self.themes[name].info.clone()
+        };
+
+        let theme = Arc::new(Theme::with_file_provider(
+            info,
+            inherits_from,
+            self.file_provider.clone(),
+        ));
+        self.rebuilt.insert(name.clone(), theme.clone());
+
+        Some(theme)
+    }
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterator over icons in themes, returned by [`Icons::find_all_icons`] and
+/// [`Icons::find_all_icons_filtered`].
+///
+/// Each item holds the theme and directory an icon was found in, and the icon itself.
+pub struct AllIcons<'a> {
+    inner: Box<dyn Iterator<Item = (Arc<Theme>, &'a DirectoryIndex, IconFile)> + 'a>,
+}
+
+impl<'a> Iterator for AllIcons<'a> {
+    type Item = (Arc<Theme>, &'a DirectoryIndex, IconFile);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// How two themes differ in the icons they provide, returned by [`Icons::coverage_diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageDiff {
+    /// Names of the icons that the first theme provides, but the second doesn't.
+    pub only_in_a: Vec<String>,
+    /// Names of the icons that the second theme provides, but the first doesn't.
+    pub only_in_b: Vec<String>,
+    /// Names of the icons that neither theme provides.
+    pub in_neither: Vec<String>,
+}
+
+/// An icon whose actual dimensions disagree with the size of its directory, returned by
+/// [`Icons::validate_sizes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeMismatch {
+    /// The icon file.
+    pub icon: IconFile,
+    /// The name of the directory the icon is in, relative to the theme's base directories.
+    pub directory_name: String,
+    /// The size the icon should have, in pixels: the directory's size multiplied by its scale.
+    pub expected: u32,
+    /// The actual width and height of the icon, in pixels.
+    pub actual: (u32, u32),
+}
+
+/// Where an icon was found, returned by [`Icons::find_icon_source`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IconSource {
+    /// The icon was found in the theme with this internal name.
+    Theme(String),
+    /// The icon is a standalone icon, see [`Icons::find_standalone_icon`].
+    Standalone,
+}
+
+/// The reason an icon couldn't be found, returned by [`Icons::try_find_icon`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FindError {
+    /// The icon name is empty, and no [fallback icon](Icons::set_fallback_icon) is configured.
+    #[error("the icon name is empty")]
+    EmptyName,
+    /// The requested theme doesn't exist. Unlike [`Icons::find_icon`], `try_find_icon` doesn't
+    /// use `hicolor` in its stead. The embedded fallback, if enabled, counts as `hicolor`.
+    #[error("the theme doesn't exist")]
+    ThemeNotFound,
+    /// The icon doesn't exist in the theme, its parents or as a standalone icon, and no
+    /// [fallback icon](Icons::set_fallback_icon) is configured.
+    #[error("the icon wasn't found")]
+    IconNotFound,
+    /// Neither the icon nor the configured [fallback icon](Icons::set_fallback_icon) were found.
+    #[error("neither the icon nor the fallback icon were found")]
+    NoFallbackAvailable,
+}
+
+/// An icon file, either on disk or in memory, along with its detected file type.
+///
+/// Icons found on the file system are backed by their path. Icons that aren't stored on disk,
+/// like the ones embedded with `Icons::with_embedded_fallback`, hold their contents in memory
+/// instead; see [`from_bytes`](IconFile::from_bytes).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct IconFile {
+    /// Where the icon's contents can be found.
+    source: Source,
+    /// The filetype of the icon, derived from its extension. May be `Png`, `Xpm` or `Svg`.
+    file_type: FileType,
+}
+
+/// Where the contents of an [`IconFile`] can be found.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum Source {
+    /// Absolute path to where the icon is found on disk.
+    Path(PathBuf),
+    /// The contents of an icon that isn't stored on disk, along with its name.
+    Bytes { name: String, bytes: Arc<[u8]> },
+}
+
+impl IconFile {
+    /// Derive the icon name from its path, or return the name it was [created](IconFile::from_bytes)
+    /// with for in-memory icons.
+    pub fn icon_name(&self) -> &str {
+        match &self.source {
+            Source::Path(path) => path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .expect("protected by type's constructor"),
+            Source::Bytes { name, .. } => name,
+        }
+    }
+
+    /// Create an `IconFile` from a filesystem path, deriving its filetype from its extension.
+    pub fn from_path(path: &Path) -> Option<IconFile> {
+        Self::from_path_buf(path.to_owned())
+    }
+
+    /// Create an `IconFile` from an owned filesystem path, deriving its filetype from its extension.
+    ///
+    /// Returns `None` if the provided path does not have a name or extension valid for icons.
+    pub fn from_path_buf(path_buf: PathBuf) -> Option<IconFile> {
+        // An icon file must have a file stem.
+        path_buf.file_stem()?;
+
+        let file_type = FileType::from_path_ext(&path_buf)?;
+
+        Some(IconFile {
+            source: Source::Path(path_buf),
+            file_type,
+        })
+    }
+
+    /// Create an `IconFile` from its path and file type, as-is.
+    ///
+    /// Unlike [`from_path_buf`](IconFile::from_path_buf), this does not check the path nor derive
+    /// the file type from it: the caller is trusted to provide a path to an icon of the given type.
+    /// This is meant for reconstructing `IconFile`s that were obtained earlier, for example
+    /// across an FFI boundary or after deserialization.
+    ///
+    /// The path must have a file name that is valid UTF-8, or [`icon_name`](IconFile::icon_name) will panic.
+    pub fn from_parts(path: PathBuf, file_type: FileType) -> IconFile {
+        IconFile {
+            source: Source::Path(path),
+            file_type,
+        }
+    }
+
+    /// Create an `IconFile` that holds its contents in memory, rather than being stored on disk,
+    /// for example for icons loaded from an archive or used in tests.
+    ///
+    /// Such icons have no [path](IconFile::path). The icons returned by lookups are always backed by
+    /// files, except for the ones embedded with `Icons::with_embedded_fallback`.
+    pub fn from_bytes(
+        icon_name: &str,
+        file_type: FileType,
+        bytes: impl Into<Arc<[u8]>>,
+    ) -> IconFile {
+        IconFile {
+            source: Source::Bytes {
+                name: icon_name.to_owned(),
+                bytes: bytes.into(),
+            },
+            file_type,
+        }
+    }
+
+    /// Turns this icon into an in-memory one if its contents are embedded in the program.
+    pub(crate) fn with_embedded_data(self, data: Option<&'static [u8]>) -> IconFile {
+        match data {
+            Some(data) => IconFile::from_bytes(self.icon_name(), self.file_type, data),
+            None => self,
+        }
+    }
+
+    /// Returns the path associated with this icon, or `None` if it is held
+    /// [in memory](IconFile::from_bytes).
+    pub fn path(&self) -> Option<&Path> {
+        match &self.source {
+            Source::Path(path) => Some(path),
+            Source::Bytes { .. } => None,
+        }
+    }
+
+    /// Returns the contents of this icon if it is held [in memory](IconFile::from_bytes), rather
+    /// than stored on disk.
+    ///
+    /// Use [`read`](IconFile::read) to get the contents of any icon.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.source {
+            Source::Path(_) => None,
+            Source::Bytes { bytes, .. } => Some(bytes),
+        }
+    }
+
+    /// Returns this icon's file type
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Returns a copy of this icon with its path resolved to the real path on disk, with all
+    /// symlinks followed. See [`std::fs::canonicalize`].
+    ///
+    /// Icons found through a symlinked theme directory have paths containing that symlink, so the
+    /// same file may be reached through different paths. Canonicalizing collapses these, for
+    /// example when collecting a set of unique icons. `self` keeps its original path.
+    /// In-memory icons are returned as-is.
+    pub fn canonicalized(&self) -> std::io::Result<IconFile> {
+        let Source::Path(path) = &self.source else {
+            return Ok(self.clone());
+        };
+
+        let path = std::fs::canonicalize(path)?;
+        let file_type = FileType::from_path_ext(&path).unwrap_or(self.file_type);
+
+        Ok(IconFile {
+            source: Source::Path(path),
+            file_type,
+        })
+    }
+
+    /// Reads the contents of this icon, either from disk or from memory.
+    pub fn read(&self) -> std::io::Result<Cow<'_, [u8]>> {
+        match &self.source {
+            Source::Path(path) => std::fs::read(path).map(Cow::Owned),
+            Source::Bytes { bytes, .. } => Ok(Cow::Borrowed(bytes)),
+        }
+    }
+
+    /// Opens the contents of this icon for reading.
+    fn open(&self) -> std::io::Result<Box<dyn Read + '_>> {
+        match &self.source {
+            Source::Path(path) => Ok(Box::new(std::fs::File::open(path)?)),
+            Source::Bytes { bytes, .. } => Ok(Box::new(&bytes[..])),
+        }
+    }
+
+    /// Returns the path of this icon, or an error if it isn't stored on disk.
+    fn file_path(&self) -> std::io::Result<&Path> {
+        self.path().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "the icon is held in memory, not stored on disk",
+            )
+        })
+    }
+
+    /// Returns the path to the `.icon` file next to this icon, if there is one.
+    ///
+    /// Such files hold extra data about the icon, like where to put text or emblems on it. Parse
+    /// them with [`IconData::parse_from_file`](crate::IconData::parse_from_file).
+    ///
+    /// Like [`metadata`](IconFile::metadata), this always checks the real file system, not the
+    /// [`FileProvider`] the icon was found through. Returns `None` for icons held
+    /// [in memory](IconFile::from_bytes).
+    pub fn metadata_file(&self) -> Option<PathBuf> {
+        Some(self.path()?.with_extension("icon")).filter(|path| path.is_file())
+    }
+
+    /// Queries the file system for metadata about this icon's file, following symlinks.
+    ///
+    /// See [`std::fs::metadata`]. Fails for icons held [in memory](IconFile::from_bytes).
+    pub fn metadata(&self) -> std::io::Result<Metadata> {
+        std::fs::metadata(self.file_path()?)
+    }
+
+    /// Returns the last modification time of this icon's file.
+    ///
+    /// See [`Metadata::modified`].
+    pub fn modified(&self) -> std::io::Result<SystemTime> {
+        self.metadata()?.modified()
+    }
+
+    /// Reads the actual width and height, in pixels, of this icon from its file's header.
+    ///
+    /// Only [`FileType::Png`] and [`FileType::Xpm`] icons have dimensions. Returns `None` for
+    /// other file types, or if the file couldn't be read or its header is invalid.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match self.file_type {
+            FileType::Png => {
+                // the signature (8 bytes) is followed by the IHDR chunk's length and type (8 bytes),
+                // after which come the width and height as big-endian integers.
+                let mut header = [0; 24];
+                self.open().ok()?.read_exact(&mut header).ok()?;
+
+                if &header[12..16] != b"IHDR" {
+                    return None;
+                }
+
+                let width = u32::from_be_bytes(header[16..20].try_into().ok()?);
+                let height = u32::from_be_bytes(header[20..24].try_into().ok()?);
+                Some((width, height))
+            }
+            FileType::Xpm => {
+                // the first string in the file holds "<width> <height> <colors> <chars per pixel>"
+                let mut header = String::new();
+                self.open()
+                    .ok()?
+                    .take(1024)
+                    .read_to_string(&mut header)
+                    .ok()?;
+
+                let values = header.split('"').nth(1)?;
+                let mut values = values.split_whitespace().map(str::parse);
+                Some((values.next()?.ok()?, values.next()?.ok()?))
+            }
+            FileType::Svg => None,
+        }
+    }
+
+    /// Picks the icon whose actual size is closest to `target_px` pixels, for when the nominal
+    /// sizes given by a theme's index can't be trusted.
+    ///
+    /// Raster icons are compared by the larger of their [dimensions](IconFile::dimensions), which
+    /// are read from disk; icons whose dimensions can't be read are skipped. [Scalable](FileType::is_scalable)
+    /// icons can be rendered at any size, so they are always considered an exact match.
+    /// If multiple icons are equally close, the first one is picked.
+    pub fn closest_to(files: &[IconFile], target_px: u32) -> Option<&IconFile> {
+        files
+            .iter()
+            .filter_map(|file| {
+                if file.file_type.is_scalable() {
+                    return Some((0, file));
+                }
+
+                let (width, height) = file.dimensions()?;
+                Some((width.max(height).abs_diff(target_px), file))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, file)| file)
+    }
+}
+
+/// A standalone icon file in a format outside the Icon Theme specification, like `.ico` or `.gif`.
+///
+/// These are only found when opted into with [`IconSearch::standalone_extensions`], see
+/// [`Icons::find_other_standalone_icon`]. Unlike [`IconFile`], the format of these files isn't known
+/// to this crate, so only their extension is kept.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OtherIconFile {
+    /// Absolute path to where the icon is found on disk.
+    path: PathBuf,
+    /// The lowercase extension of the file, without the leading dot.
+    extension: String,
+}
+
+impl OtherIconFile {
+    /// Create an `OtherIconFile` from a filesystem path, keeping its lowercase extension.
+    ///
+    /// Returns `None` if the path does not have a valid UTF-8 name and extension.
+    pub fn from_path_buf(path: PathBuf) -> Option<OtherIconFile> {
+        path.file_stem()?.to_str()?;
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        Some(OtherIconFile { path, extension })
+    }
+
+    /// Derive the icon name from its path.
+    pub fn icon_name(&self) -> &str {
+        self.path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .expect("protected by type's constructor")
+    }
+
+    /// Returns the path of this icon.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the lowercase extension of this icon's file, without the leading dot, e.g. `"ico"`.
+    pub fn extension(&self) -> &str {
+        &self.extension
+    }
+}
+
+/// Supported image file formats for icons.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FileType {
+    /// `.png` files (Portable Network Graphics)
+    Png,
+    /// `.xpm` files (X PixMap), an image file format used by the X window system.
     Xpm,
     /// `.svg` files (Scalable Vector Graphics), for images that can be scaled to an arbitrary size.
     Svg,
 }
 
-impl FileType {
-    /// Get a `FileType` from the file extension of some path.
-    pub fn from_path_ext(path: &Path) -> Option<Self> {
-        let ext = path.extension()?;
-        let ext = ext.to_str()?;
+impl FileType {
+    /// Get a `FileType` from the file extension of some path.
+    pub fn from_path_ext(path: &Path) -> Option<Self> {
+        let ext = path.extension()?;
+        let ext = ext.to_str()?;
+
+        ext.parse().ok()
+    }
+
+    /// Provides a string representation of this `FileType`.
+    ///
+    /// Each file type is mapped to its canonical, lowercase file extension ("png", "xpm", "svg").
+    pub fn ext(&self) -> &str {
+        match self {
+            FileType::Png => "png",
+            FileType::Xpm => "xpm",
+            FileType::Svg => "svg",
+        }
+    }
+
+    /// Returns `true` for file types that can be scaled to any size without loss, i.e. [`FileType::Svg`].
+    pub fn is_scalable(&self) -> bool {
+        matches!(self, FileType::Svg)
+    }
+
+    /// Returns `true` for file types of images made up of pixels, which can't be scaled without
+    /// loss. This is every type that isn't [scalable](FileType::is_scalable).
+    pub fn is_raster(&self) -> bool {
+        !self.is_scalable()
+    }
+
+    /// Returns an array of all file types that icons may appear as.
+    pub const fn types() -> [FileType; 3] {
+        [FileType::Png, FileType::Xpm, FileType::Svg]
+    }
+}
+
+/// Parses a `FileType` from its file extension, ignoring case. See [`FileType::ext`].
+impl TryFrom<&str> for FileType {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        FileType::types()
+            .into_iter()
+            .find(|ft| ft.ext().eq_ignore_ascii_case(value))
+            .ok_or(())
+    }
+}
+
+impl FromStr for FileType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.try_into()
+    }
+}
+
+impl Display for FileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ext().to_owned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::search::test::{test_dir, test_search, write_theme};
+    use crate::{
+        Context, CoverageDiff, FileType, FindError, IconFile, IconSearch, IconSource, Icons,
+        LookupOutcome, Theme,
+    };
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_find_all_icons() {
+        let icons = test_search().search().icons();
+        let mut map: HashMap<String, Vec<IconFile>> = Default::default();
+        for (_, _, icon) in icons.find_all_icons() {
+            map.entry(icon.icon_name().to_owned())
+                .or_insert_with(Default::default)
+                .push(icon)
+        }
+
+        // "beautiful sunset" has 3 icons:
+        assert_eq!(map["beautiful sunset"].len(), 3);
+        // "happy" has 2:
+        assert_eq!(map["happy"].len(), 2);
+        // and "pixel" appears once:
+        assert_eq!(map["pixel"].len(), 1);
+
+        // "beautiful sunset" has one .xpm file:
+        assert_eq!(
+            map["beautiful sunset"]
+                .iter()
+                .filter(|ico| ico.file_type() == crate::FileType::Xpm)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_estimate_icon_count() {
+        let icons = test_search().search().icons();
+        assert_eq!(icons.estimate_icon_count(), icons.find_all_icons().count());
+
+        // files that aren't icons are counted too
+        let base_dir = test_dir("estimate-icon-count");
+        write_theme(
+            &base_dir,
+            "Counted",
+            "[Icon Theme]\nName=Counted\nDirectories=16x16\n\n[16x16]\nSize=16\n",
+        );
+        let dir = base_dir.join("Counted/16x16");
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["a.png", "b.svg", "README"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        assert_eq!(icons.estimate_icon_count(), 3);
+        assert_eq!(icons.find_all_icons().count(), 2);
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(super::glob_matches("folder-*", "folder-documents"));
+        assert!(super::glob_matches("folder-*", "folder-"));
+        assert!(!super::glob_matches("folder-*", "folder"));
+        assert!(super::glob_matches("*-symbolic", "edit-copy-symbolic"));
+        assert!(super::glob_matches("a*b*c", "a-b-b-c"));
+        assert!(!super::glob_matches("a*b*c", "a-b-b-d"));
+        assert!(super::glob_matches("h?ppy", "happy"));
+        assert!(!super::glob_matches("h?ppy", "hppy"));
+        assert!(super::glob_matches("*", ""));
+        assert!(super::glob_matches("exact", "exact"));
+        assert!(!super::glob_matches("exact", "exactly"));
+    }
+
+    #[test]
+    fn test_find_icons_matching() {
+        let icons = test_search().search().icons();
+
+        let matches = icons
+            .find_icons_matching("*a*", "TestTheme")
+            .map(|(name, icon)| (name, icon.file_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            matches,
+            [
+                ("beautiful sunset".to_owned(), FileType::Png),
+                ("beautiful sunset".to_owned(), FileType::Png),
+                ("beautiful sunset".to_owned(), FileType::Xpm),
+                ("happy".to_owned(), FileType::Png),
+                ("happy".to_owned(), FileType::Png),
+            ]
+        );
+
+        // `pixel` is found in the parent theme
+        let names = icons
+            .find_icons_matching("p?xel", "TestTheme")
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["pixel"]);
+
+        assert_eq!(
+            icons.find_icons_matching("folder-*", "TestTheme").count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_present_names() {
+        let mut icons = test_search().search().icons();
+
+        // "pixel" is inherited from OtherTheme
+        let present = icons.present_names(["happy", "missing", "pixel"], "TestTheme");
+        assert_eq!(present, ["happy", "pixel"]);
+
+        // names are present if their alias is, but not because of the fallback icon
+        icons.add_alias("smiley", "happy");
+        icons.set_fallback_icon(Some("pixel"));
+        let present = icons.present_names(["smiley", "missing"], "TestTheme");
+        assert_eq!(present, ["smiley"]);
+        assert!(icons.find_icon("smiley", 16, 1, "TestTheme").is_some());
+    }
+
+    #[test]
+    fn test_coverage_diff() {
+        let icons = test_search().search().icons();
+
+        let diff = icons.coverage_diff(
+            &["happy", "pixel", "missing", "beautiful sunset"],
+            "TestTheme",
+            "OtherTheme",
+        );
+        assert_eq!(
+            diff,
+            CoverageDiff {
+                only_in_a: vec!["happy".to_owned(), "beautiful sunset".to_owned()],
+                only_in_b: vec![],
+                in_neither: vec!["missing".to_owned()],
+            }
+        );
+
+        let diff = icons.coverage_diff(&["happy", "pixel"], "OtherTheme", "TestTheme");
+        assert_eq!(diff.only_in_a, Vec::<String>::new());
+        assert_eq!(diff.only_in_b, ["happy"]);
+    }
+
+    #[test]
+    fn test_closest_to() {
+        let icons = test_search().search().icons();
+        let small = icons.find_icon("happy", 16, 1, "TestTheme").unwrap();
+        let big = icons.find_icon("happy", 32, 1, "TestTheme").unwrap();
+        let xpm = icons
+            .theme("TestTheme")
+            .unwrap()
+            .find_icon_typed("beautiful sunset", 64, 1, &[FileType::Xpm])
+            .unwrap();
+
+        assert_eq!(small.dimensions(), Some((16, 16)));
+        assert_eq!(xpm.dimensions(), Some((64, 64)));
+
+        let files = [small.clone(), big.clone(), xpm.clone()];
+        assert_eq!(IconFile::closest_to(&files, 20), Some(&small));
+        assert_eq!(IconFile::closest_to(&files, 30), Some(&big));
+        assert_eq!(IconFile::closest_to(&files, 256), Some(&xpm));
+        assert_eq!(IconFile::closest_to(&[], 16), None);
+    }
+
+    #[test]
+    fn test_file_type_scalability() {
+        assert!(FileType::Svg.is_scalable());
+        assert!(!FileType::Svg.is_raster());
+
+        for file_type in [FileType::Png, FileType::Xpm] {
+            assert!(file_type.is_raster());
+            assert!(!file_type.is_scalable());
+        }
+    }
+
+    #[test]
+    fn test_find_icon_with_extra_dirs() {
+        let icons = test_search().search().icons();
+        let extra_dir =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/symlinked_icons/128x128");
+
+        assert!(
+            icons
+                .find_icon("beautiful sunset", 16, 1, "OtherTheme")
+                .is_none()
+        );
+
+        let icon = icons
+            .find_icon_with_extra_dirs("beautiful sunset", 16, 1, "OtherTheme", &[&extra_dir])
+            .unwrap();
+        assert_eq!(icon.path().unwrap(), extra_dir.join("beautiful sunset.png"));
+    }
+
+    #[test]
+    fn test_canonicalized() {
+        let icons = test_search().search().icons();
+        let icon = icons
+            .find_icon("beautiful sunset", 128, 1, "TestTheme")
+            .unwrap();
+        assert!(
+            icon.path()
+                .unwrap()
+                .ends_with("test_icons_alt/TestTheme/128x128/beautiful sunset.png")
+        );
+
+        let canonical = icon.canonicalized().unwrap();
+        let expected = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/symlinked_icons/128x128/beautiful sunset.png");
+        assert_eq!(canonical.path().unwrap(), expected.canonicalize().unwrap());
+        assert_eq!(canonical.file_type(), icon.file_type());
+    }
+
+    #[test]
+    fn test_icon_from_bytes() {
+        let icons = test_search().search().icons();
+        let on_disk = icons.find_icon("happy", 16, 1, "TestTheme").unwrap();
+        let bytes = on_disk.read().unwrap().into_owned();
+
+        let icon = IconFile::from_bytes("happy", FileType::Png, bytes.clone());
+        assert_eq!(icon.icon_name(), "happy");
+        assert_eq!(icon.path(), None);
+        assert_eq!(icon.bytes(), Some(&bytes[..]));
+        assert_eq!(icon.read().unwrap(), bytes);
+        assert_eq!(icon.dimensions(), on_disk.dimensions());
+        assert_eq!(icon.canonicalized().unwrap(), icon);
+        assert_eq!(icon.metadata_file(), None);
+        assert!(icon.metadata().is_err());
+
+        assert_eq!(on_disk.bytes(), None);
+    }
+
+    #[test]
+    fn test_alias() {
+        let mut icons = test_search().search().icons();
+        assert!(icons.find_icon("smiley", 16, 1, "TestTheme").is_none());
+
+        icons.add_alias("smiley", "happy");
+        icons.add_alias("happy", "pixel");
+
+        let icon = icons.find_icon("smiley", 16, 1, "TestTheme").unwrap();
+        assert_eq!(icon.icon_name(), "happy");
+
+        // names that exist are not redirected
+        let icon = icons.find_icon("happy", 16, 1, "TestTheme").unwrap();
+        assert_eq!(icon.icon_name(), "happy");
+    }
+
+    #[test]
+    fn test_metadata() {
+        let icons = test_search().search().icons();
+        let icon = icons.find_icon("happy", 16, 1, "TestTheme").unwrap();
+
+        let metadata = icon.metadata().unwrap();
+        assert!(metadata.is_file());
+        assert_eq!(icon.modified().unwrap(), metadata.modified().unwrap());
+
+        let missing = IconFile::from_parts("/does/not/exist.png".into(), crate::FileType::Png);
+        assert!(missing.metadata().is_err());
+        assert!(missing.modified().is_err());
+    }
+
+    #[test]
+    fn test_find_icon_deadline() {
+        let icons = test_search().search().icons();
+
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let icon = icons.find_icon_deadline("pixel", 1, 1, "TestTheme", deadline);
+        assert_eq!(icon, icons.find_icon("pixel", 1, 1, "TestTheme"));
+        assert!(icon.is_some());
+
+        let deadline = Instant::now();
+        let icon = icons.find_icon_deadline("pixel", 1, 1, "TestTheme", deadline);
+        assert!(icon.is_none());
+    }
+
+    #[test]
+    fn test_theme_chain() {
+        let icons = test_search().search().icons();
+
+        assert_eq!(
+            icons.theme_chain("TestTheme").unwrap(),
+            ["TestTheme", "OtherTheme"]
+        );
+        assert_eq!(icons.theme_chain("OtherTheme").unwrap(), ["OtherTheme"]);
+        assert!(icons.theme_chain("Missing").is_none());
+    }
+
+    #[test]
+    fn test_try_find_icon() {
+        let mut icons = test_search().search().icons();
+
+        let icon = icons.try_find_icon("happy", 16, 1, "TestTheme");
+        assert_eq!(icon.ok(), icons.find_icon("happy", 16, 1, "TestTheme"));
+
+        assert_eq!(
+            icons.try_find_icon("", 16, 1, "TestTheme"),
+            Err(FindError::EmptyName)
+        );
+        assert_eq!(
+            icons.try_find_icon("missing", 16, 1, "TestTheme"),
+            Err(FindError::IconNotFound)
+        );
+        assert_eq!(
+            icons.try_find_icon("happy", 16, 1, "Missing"),
+            Err(FindError::ThemeNotFound)
+        );
+        // the name is checked before the theme
+        assert_eq!(
+            icons.try_find_icon("", 16, 1, "Missing"),
+            Err(FindError::EmptyName)
+        );
 
-        if ext.eq_ignore_ascii_case("png") {
-            Some(FileType::Png)
-        } else if ext.eq_ignore_ascii_case("xpm") {
-            Some(FileType::Xpm)
-        } else if ext.eq_ignore_ascii_case("svg") {
-            Some(FileType::Svg)
-        } else {
-            None
+        icons.set_fallback_icon(Some("also-missing"));
+        assert_eq!(
+            icons.try_find_icon("missing", 16, 1, "TestTheme"),
+            Err(FindError::NoFallbackAvailable)
+        );
+
+        icons.set_fallback_icon(Some("pixel"));
+        let icon = icons.try_find_icon("", 16, 1, "TestTheme").unwrap();
+        assert_eq!(icon.icon_name(), "pixel");
+
+        // a missing theme isn't reported as a missing icon, even though `find_icon` would look in
+        // hicolor instead
+        let base_dir = test_dir("try-find-icon");
+        write_theme(
+            &base_dir,
+            "hicolor",
+            "[Icon Theme]\nName=Hicolor\nDirectories=\n",
+        );
+        let icons = test_search().add_directories([&base_dir]).search().icons();
+        assert!(icons.theme("hicolor").is_some());
+        assert_eq!(
+            icons.try_find_icon("missing", 16, 1, "Missing"),
+            Err(FindError::ThemeNotFound)
+        );
+        assert_eq!(
+            icons.try_find_icon("missing", 16, 1, "hicolor"),
+            Err(FindError::IconNotFound)
+        );
+    }
+
+    #[test]
+    fn test_themes_providing() {
+        let icons = test_search().search().icons();
+
+        assert_eq!(icons.themes_providing("happy"), ["TestTheme"]);
+        // TestTheme only finds "pixel" through OtherTheme
+        assert_eq!(icons.themes_providing("pixel"), ["OtherTheme"]);
+        assert!(icons.themes_providing("missing").is_empty());
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let other = Theme::load_from_dir(&root.join("test_icons_alt/OtherTheme")).unwrap();
+        let other = Arc::new(other);
+        let test = Theme::load_from_dir(&root.join("test_icons/TestTheme")).unwrap();
+        let test = Arc::new(Theme::new(test.info, vec![other.clone()]));
+
+        let themes = HashMap::from([("TestTheme".into(), test), ("OtherTheme".into(), other)]);
+        let icons = Icons::from_parts(HashMap::new(), themes);
+
+        assert_eq!(icons.theme_count(), 2);
+        assert!(icons.find_icon("pixel", 1, 1, "TestTheme").is_some());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_from_parts_missing_parent() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let other = Theme::load_from_dir(&root.join("test_icons_alt/OtherTheme")).unwrap();
+        let other = Arc::new(other);
+        let test = Theme::load_from_dir(&root.join("test_icons/TestTheme")).unwrap();
+        let test = Arc::new(Theme::new(test.info, vec![other]));
+
+        Icons::from_parts(HashMap::new(), HashMap::from([("TestTheme".into(), test)]));
+    }
+
+    #[test]
+    fn test_find_standalone_first() {
+        let dir = test_dir("standalone-first");
+        std::fs::write(dir.join("happy.png"), "").unwrap();
+
+        let icons = test_search().add_directories([&dir]).search().icons();
+
+        let icon = icons.find_icon("happy", 16, 1, "TestTheme").unwrap();
+        assert!(
+            icon.path()
+                .unwrap()
+                .ends_with("TestTheme/16x16/α/happy.png")
+        );
+
+        let icon = icons
+            .find_standalone_first("happy", 16, 1, "TestTheme")
+            .unwrap();
+        assert_eq!(icon.path().unwrap(), dir.join("happy.png"));
+
+        let icon = icons.find_standalone_first("pixel", 1, 1, "TestTheme");
+        assert_eq!(icon, icons.find_icon("pixel", 1, 1, "TestTheme"));
+    }
+
+    #[test]
+    fn test_best_icon() {
+        let base_dir = test_dir("best-icon");
+        let index = |name: &str| {
+            format!(
+                "[Icon Theme]\nName={name}\nDirectories=16x16,32x32\n\n\
+                 [16x16]\nSize=16\nType=Fixed\n\n[32x32]\nSize=32\nType=Fixed\n"
+            )
+        };
+        for (theme, icons) in [
+            ("Desktop", &["16x16/shared", "16x16/sized"][..]),
+            ("hicolor", &["16x16/shared", "16x16/generic"]),
+            ("Other", &["32x32/sized", "32x32/generic", "32x32/other"]),
+        ] {
+            write_theme(&base_dir, theme, &index(theme));
+            for icon in icons {
+                let path = base_dir.join(theme).join(format!("{icon}.png"));
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, "").unwrap();
+            }
         }
+        write_theme(&base_dir, "default", "[Icon Theme]\nInherits=Desktop\n");
+
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        let find = |name: &str, size: u32| {
+            let icon = icons.best_icon(name, size, 1)?;
+            let theme = icon.path().unwrap().parent()?.parent()?.file_name()?;
+            Some(theme.to_string_lossy().into_owned())
+        };
+
+        // exact matches come from the most preferred theme
+        assert_eq!(find("shared", 16).as_deref(), Some("Desktop"));
+        assert_eq!(find("generic", 16).as_deref(), Some("hicolor"));
+        assert_eq!(find("sized", 16).as_deref(), Some("Desktop"));
+        // otherwise, the closest match of any theme wins
+        assert_eq!(find("sized", 32).as_deref(), Some("Other"));
+        assert_eq!(find("generic", 32).as_deref(), Some("Other"));
+        // which is the most preferred theme's on ties
+        assert_eq!(find("shared", 32).as_deref(), Some("Desktop"));
+        assert_eq!(find("other", 16).as_deref(), Some("Other"));
+        assert_eq!(find("missing", 16), None);
+        assert_eq!(find("", 16), None);
     }
 
-    /// Provides a string representation of this `FileType`.
-    ///
-    /// Each file type is mapped to its canonical, lowercase file extension ("png", "xpm", "svg").
-    pub fn ext(&self) -> &str {
-        match self {
-            FileType::Png => "png",
-            FileType::Xpm => "xpm",
-            FileType::Svg => "svg",
+    #[test]
+    fn test_validate_sizes() {
+        let base_dir = test_dir("validate-sizes");
+        write_theme(
+            &base_dir,
+            "Sizes",
+            "[Icon Theme]\nName=Sizes\nDirectories=16x16,16x16@2,scalable\n\n\
+             [16x16]\nSize=16\n\n\
+             [16x16@2]\nSize=16\nScale=2\n\n\
+             [scalable]\nSize=16\nType=Scalable\n",
+        );
+        let happy = |size: u32| {
+            Path::new(env!("CARGO_MANIFEST_DIR")).join(format!(
+                "resources/test_icons/TestTheme/{size}x{size}/{}/happy.png",
+                if size == 16 { "α" } else { "foo" }
+            ))
+        };
+        let theme_dir = base_dir.join("Sizes");
+        for dir in ["16x16", "16x16@2", "scalable"] {
+            std::fs::create_dir_all(theme_dir.join(dir)).unwrap();
+        }
+        let copy = |size: u32, to: &str| std::fs::copy(happy(size), theme_dir.join(to)).unwrap();
+        copy(16, "16x16/right.png");
+        copy(32, "16x16/wrong.png");
+        copy(32, "16x16@2/right.png");
+        copy(16, "16x16@2/wrong.png");
+        copy(32, "scalable/any.png");
+        std::fs::write(
+            theme_dir.join("16x16/small.xpm"),
+            "/* XPM */\nstatic char *small[] = {\n\"8 8 1 1\",\n};\n",
+        )
+        .unwrap();
+        // unreadable icons are skipped
+        std::fs::write(theme_dir.join("16x16/empty.png"), "").unwrap();
+
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        let mismatches = icons
+            .validate_sizes("Sizes")
+            .into_iter()
+            .map(|mismatch| {
+                (
+                    mismatch.directory_name,
+                    mismatch.icon.icon_name().to_owned(),
+                    mismatch.expected,
+                    mismatch.actual,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            mismatches,
+            [
+                ("16x16".into(), "small".into(), 16, (8, 8)),
+                ("16x16".into(), "wrong".into(), 16, (32, 32)),
+                ("16x16@2".into(), "wrong".into(), 32, (16, 16)),
+            ]
+        );
+        assert!(icons.validate_sizes("Missing").is_empty());
+    }
+
+    #[test]
+    fn test_find_icon_exact() {
+        let icons = test_search().search().icons();
+        let find = |name: &str, size: u32, scale: u32| {
+            icons.find_icon_exact(name, size, scale, "TestTheme")
+        };
+
+        assert_eq!(
+            find("happy", 16, 1),
+            icons.find_icon("happy", 16, 1, "TestTheme")
+        );
+        assert!(find("happy", 32, 1).is_some());
+        // the threshold of 2 pixels still counts as an exact match
+        assert!(find("happy", 18, 1).is_some());
+        assert!(icons.find_icon("happy", 24, 1, "TestTheme").is_some());
+        assert!(find("happy", 24, 1).is_none());
+        assert!(find("happy", 16, 2).is_none());
+        // parents are searched
+        assert!(find("pixel", 1, 1).is_some());
+        assert!(find("pixel", 16, 1).is_none());
+    }
+
+    #[test]
+    fn test_find_icon_no_upscale() {
+        let base_dir = test_dir("no-upscale");
+        write_theme(
+            &base_dir,
+            "Sizes",
+            "[Icon Theme]\nName=Sizes\nDirectories=16,24,32,48\n\n\
+             [16]\nSize=16\nType=Fixed\n\n[24]\nSize=24\nType=Fixed\n\n\
+             [32]\nSize=32\nType=Fixed\n\n[48]\nSize=48\nType=Fixed\n",
+        );
+        for (dir, icons) in [
+            ("16", &["smaller"][..]),
+            ("24", &["both", "smaller"]),
+            ("32", &["exact"]),
+            ("48", &["both", "exact"]),
+        ] {
+            let dir = base_dir.join("Sizes").join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            for icon in icons {
+                std::fs::write(dir.join(format!("{icon}.png")), "").unwrap();
+            }
         }
+
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        let find = |name: &str| {
+            let icon = icons.find_icon_no_upscale(name, 32, 1, "Sizes")?;
+            let dir = icon.path().unwrap().parent()?.file_name()?;
+            Some(dir.to_string_lossy().into_owned())
+        };
+
+        assert_eq!(find("exact").as_deref(), Some("32"));
+        // 24 is closer, but would have to be upscaled
+        assert_eq!(
+            icons.find_icon("both", 32, 1, "Sizes").unwrap(),
+            IconFile::from_path(&base_dir.join("Sizes/24/both.png")).unwrap()
+        );
+        assert_eq!(find("both").as_deref(), Some("48"));
+        // without larger icons, the largest smaller one is used
+        assert_eq!(find("smaller").as_deref(), Some("24"));
+        assert_eq!(find("missing"), None);
     }
 
-    /// Returns an array of all file types that icons may appear as.
-    pub const fn types() -> [FileType; 3] {
-        [FileType::Png, FileType::Xpm, FileType::Svg]
+    #[test]
+    fn test_find_icon_context() {
+        let base_dir = test_dir("icon-context");
+        write_theme(
+            &base_dir,
+            "Contexts",
+            "[Icon Theme]\nName=Contexts\nDirectories=places/16,apps/32,48\n\n\
+             [places/16]\nSize=16\nContext=Places\n\n\
+             [apps/32]\nSize=32\nContext=Applications\n\n\
+             [48]\nSize=48\n",
+        );
+        for dir in ["places/16", "apps/32", "48"] {
+            let dir = base_dir.join("Contexts").join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("folder.png"), "").unwrap();
+        }
+        let standalone_dir = base_dir.join("standalone");
+        std::fs::create_dir_all(&standalone_dir).unwrap();
+        std::fs::write(standalone_dir.join("loose.png"), "").unwrap();
+
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .add_directories([&standalone_dir])
+            .search()
+            .icons();
+        let context = |name: &str, size: u32| {
+            let (_, context) = icons.find_icon_context(name, size, 1, "Contexts")?;
+            Some(context)
+        };
+
+        assert_eq!(context("folder", 16), Some(Some("Places".into())));
+        assert_eq!(context("folder", 32), Some(Some("Applications".into())));
+        assert_eq!(context("folder", 48), Some(None));
+        assert_eq!(context("loose", 16), Some(None));
+        assert_eq!(context("missing", 16), None);
     }
-}
 
-impl Display for FileType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.ext().to_owned())
+    #[test]
+    fn test_refresh_if_changed() {
+        let base_dir = test_dir("refresh-if-changed");
+        let index = |name: &str| {
+            format!("[Icon Theme]\nName={name}\nDirectories=16x16\n\n[16x16]\nSize=16\n")
+        };
+        write_theme(&base_dir, "Changing", &index("Changing"));
+        std::fs::create_dir_all(base_dir.join("Changing/16x16")).unwrap();
+        std::fs::write(base_dir.join("Changing/16x16/icon.png"), "").unwrap();
+        // doesn't exist yet
+        let extra_dir = base_dir.join("extra");
+
+        let mut icons = IconSearch::new_from(vec![base_dir.to_path_buf(), extra_dir.clone()])
+            .search()
+            .icons();
+        icons.add_alias("alias", "icon");
+        // checking the modification times is counted as stats: the two search directories, and
+        // the theme's directory and index
+        let stat_calls = icons.scan_stats().stat_calls;
+        assert!(!icons.needs_refresh());
+        assert_eq!(icons.scan_stats().stat_calls, stat_calls + 4);
+        assert!(!icons.refresh_if_changed());
+        assert_eq!(icons.scan_stats().stat_calls, stat_calls + 8);
+
+        write_theme(&extra_dir, "New", &index("New"));
+        assert!(icons.theme("New").is_none());
+        assert!(icons.needs_refresh());
+
+        assert!(icons.refresh_if_changed());
+        assert!(icons.theme("New").is_some());
+        // settings are kept
+        assert!(icons.find_icon("alias", 16, 1, "Changing").is_some());
+        assert!(!icons.needs_refresh());
+
+        // editing an index changes its modification time
+        let index_file = std::fs::File::options()
+            .write(true)
+            .open(base_dir.join("Changing/index.theme"))
+            .unwrap();
+        index_file.set_modified(std::time::UNIX_EPOCH).unwrap();
+        assert!(icons.needs_refresh());
+
+        // changes made before the first check are detected too
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        index_file
+            .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1))
+            .unwrap();
+        assert!(icons.needs_refresh());
+
+        // without a search to repeat, there's nothing to refresh
+        let icons = Icons::from_parts(HashMap::new(), HashMap::new());
+        assert!(!icons.needs_refresh());
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::IconFile;
-    use crate::search::test::test_search;
-    use std::collections::HashMap;
+    #[test]
+    fn test_find_icon_with_defaults() {
+        let base_dir = test_dir("with-defaults");
+        let index = |name: &str| {
+            format!("[Icon Theme]\nName={name}\nDirectories=16x16\n\n[16x16]\nSize=16\n")
+        };
+        for (theme, icons) in [
+            ("App", &["app"][..]),
+            ("Desktop", &["shared", "desktop"]),
+            ("hicolor", &["shared", "desktop", "generic"]),
+        ] {
+            write_theme(&base_dir, theme, &index(theme));
+            let dir = base_dir.join(theme).join("16x16");
+            std::fs::create_dir_all(&dir).unwrap();
+            for icon in icons {
+                std::fs::write(dir.join(format!("{icon}.png")), "").unwrap();
+            }
+        }
+        write_theme(&base_dir, "default", "[Icon Theme]\nInherits=Desktop\n");
+
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        let find = |name: &str, primary: &str| {
+            let icon = icons.find_icon_with_defaults(name, 16, 1, primary)?;
+            let theme = icon.path().unwrap().parent()?.parent()?.file_name()?;
+            Some(theme.to_string_lossy().into_owned())
+        };
+
+        assert_eq!(find("app", "App").as_deref(), Some("App"));
+        // the default theme is checked before hicolor, which `App` inherits from implicitly
+        assert_eq!(find("shared", "App").as_deref(), Some("Desktop"));
+        assert_eq!(find("generic", "App").as_deref(), Some("hicolor"));
+        assert_eq!(find("missing", "App"), None);
+        // a missing primary theme is skipped
+        assert_eq!(find("desktop", "Missing").as_deref(), Some("Desktop"));
+
+        // with the default theme as primary theme, this is the same as `find_icon`
+        assert_eq!(
+            icons.find_icon("shared", 16, 1, "Desktop"),
+            icons.find_icon_with_defaults("shared", 16, 1, "Desktop")
+        );
+    }
 
     #[test]
-    fn test_find_all_icons() {
+    fn test_find_app_icon() {
+        let dir = test_dir("app-icon");
+        for file in ["nautilus.png", "org.gnome.Calculator.png", "firefox.png"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let mut icons = test_search().add_directories([&dir]).search().icons();
+        let find = |icons: &Icons, app_id: &str| {
+            let icon = icons.find_app_icon(app_id, 16, 1, "TestTheme")?;
+            Some(
+                icon.path()
+                    .unwrap()
+                    .file_name()?
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        };
+
+        assert_eq!(
+            find(&icons, "org.gnome.Calculator").as_deref(),
+            Some("org.gnome.Calculator.png")
+        );
+        assert_eq!(
+            find(&icons, "org.gnome.Nautilus").as_deref(),
+            Some("nautilus.png")
+        );
+        assert_eq!(find(&icons, "Firefox").as_deref(), Some("firefox.png"));
+        // themed icons are found too
+        assert_eq!(
+            find(&icons, "com.example.Happy").as_deref(),
+            Some("happy.png")
+        );
+        assert_eq!(find(&icons, "com.example.Missing"), None);
+        assert_eq!(find(&icons, ""), None);
+
+        icons.set_fallback_icon(Some("firefox"));
+        assert_eq!(
+            find(&icons, "com.example.Missing").as_deref(),
+            Some("firefox.png")
+        );
+    }
+
+    #[test]
+    fn test_find_icon_with_context_fallback() {
+        let dir = test_dir("context-fallback");
+        for file in ["folder.png", "image-x-generic.png", "text-x-generic.png"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let icons = test_search().add_directories([&dir]).search().icons();
+        let find = |icon_name: &str, context: Context| {
+            let icon =
+                icons.find_icon_with_context_fallback(icon_name, 16, 1, "TestTheme", context)?;
+            Some(icon.icon_name().to_owned())
+        };
+
+        assert_eq!(find("happy", Context::Places).as_deref(), Some("happy"));
+        assert_eq!(find("missing", Context::Places).as_deref(), Some("folder"));
+        assert_eq!(
+            find("image-x-custom", Context::MimeTypes).as_deref(),
+            Some("image-x-generic")
+        );
+        // there is no `application-x-generic` icon to fall back to
+        assert_eq!(
+            find("application-x-custom", Context::MimeTypes).as_deref(),
+            Some("text-x-generic")
+        );
+        assert_eq!(find("missing", Context::Actions), None);
+        assert_eq!(find("", Context::Places), None);
+
+        assert_eq!(Context::try_from("MimeTypes"), Ok(Context::MimeTypes));
+        assert_eq!(Context::try_from("Unknown"), Err(()));
+    }
+
+    #[test]
+    fn test_find_icon_source() {
+        let dir = test_dir("icon-source");
+        std::fs::write(dir.join("loose.png"), "").unwrap();
+
+        let icons = test_search().add_directories([&dir]).search().icons();
+
+        let (_, source) = icons.find_icon_source("happy", 16, 1, "TestTheme").unwrap();
+        assert_eq!(source, IconSource::Theme("TestTheme".into()));
+
+        let (_, source) = icons.find_icon_source("pixel", 1, 1, "TestTheme").unwrap();
+        assert_eq!(source, IconSource::Theme("OtherTheme".into()));
+
+        let (icon, source) = icons.find_icon_source("loose", 16, 1, "TestTheme").unwrap();
+        assert_eq!(icon.path().unwrap(), dir.join("loose.png"));
+        assert_eq!(source, IconSource::Standalone);
+
+        assert!(
+            icons
+                .find_icon_source("missing", 16, 1, "TestTheme")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_find_icon_path() {
         let icons = test_search().search().icons();
-        let mut map: HashMap<String, Vec<IconFile>> = Default::default();
-        for (_, _, icon) in icons.find_all_icons() {
-            map.entry(icon.icon_name().to_owned())
-                .or_insert_with(Default::default)
-                .push(icon)
+
+        let path = icons.find_icon_path("happy", 16, 1, "TestTheme").unwrap();
+        assert!(path.ends_with("TestTheme/16x16/α/happy.png"));
+        assert!(
+            icons
+                .find_icon_path("missing", 16, 1, "TestTheme")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_fallback_icon() {
+        let mut icons = test_search().search().icons();
+        icons.set_fallback_icon(Some("pixel"));
+
+        let icon = icons
+            .find_icon("does-not-exist", 16, 1, "TestTheme")
+            .unwrap();
+        assert_eq!(icon.icon_name(), "pixel");
+        let icon = icons.find_icon("", 16, 1, "TestTheme").unwrap();
+        assert_eq!(icon.icon_name(), "pixel");
+        let icon = icons.find_icon("happy", 16, 1, "TestTheme").unwrap();
+        assert_eq!(icon.icon_name(), "happy");
+
+        assert!(
+            icons
+                .find_icon_strict("does-not-exist", 16, 1, "TestTheme")
+                .is_none()
+        );
+
+        icons.set_fallback_icon(None);
+        assert!(
+            icons
+                .find_icon("does-not-exist", 16, 1, "TestTheme")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_inheritance_graph() {
+        let icons = test_search().search().icons();
+
+        assert_eq!(
+            icons.inheritance_graph(),
+            [
+                ("OtherTheme".to_owned(), vec![]),
+                ("TestTheme".to_owned(), vec!["OtherTheme".to_owned()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_standalone_icon_types() {
+        let base_dir = test_dir("standalone-types");
+        for file in ["app.png", "app.svg", "other.xpm"] {
+            std::fs::write(base_dir.join(file), "").unwrap();
         }
 
-        // "beautiful sunset" has 3 icons:
-        assert_eq!(map["beautiful sunset"].len(), 3);
-        // "happy" has 2:
-        assert_eq!(map["happy"].len(), 2);
-        // and "pixel" appears once:
-        assert_eq!(map["pixel"].len(), 1);
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        assert_eq!(icons.standalone_icons["app"].len(), 2);
+
+        let mut files = icons
+            .standalone_icons_iter()
+            .map(|icon| icon.path().unwrap().file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["app.png", "app.svg", "other.xpm"]);
+
+        let icon = icons.find_standalone_icon("app").unwrap();
+        assert_eq!(icon.file_type(), FileType::Png);
+        let icon = icons
+            .find_standalone_icon_typed("app", &[FileType::Svg, FileType::Png])
+            .unwrap();
+        assert_eq!(icon.file_type(), FileType::Svg);
+        assert!(
+            icons
+                .find_standalone_icon_typed("other", &[FileType::Png])
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_find_icon_within() {
+        let icons = test_search().search().icons();
+
+        // "happy" exists at 16x16 and 32x32 (both with a threshold of 2)
+        let icon = icons
+            .find_icon_within("happy", 16, 1, "TestTheme", 0)
+            .unwrap();
+        assert!(icon.path().unwrap().ends_with("16x16/α/happy.png"));
+        // 24 is 8 pixels away from both
+        assert!(
+            icons
+                .find_icon_within("happy", 24, 1, "TestTheme", 7)
+                .is_none()
+        );
+        assert!(
+            icons
+                .find_icon_within("happy", 24, 1, "TestTheme", 8)
+                .is_some()
+        );
+
+        // "pixel" only exists in OtherTheme, at 1x1
+        assert!(
+            icons
+                .find_icon_within("pixel", 1, 1, "TestTheme", 0)
+                .is_some()
+        );
+        assert!(
+            icons
+                .find_icon_within("pixel", 64, 1, "TestTheme", 10)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_all_directories() {
+        let icons = test_search().search().icons();
+
+        let mut directories = icons
+            .all_directories()
+            .map(|(theme, dir)| (theme.info.internal_name.clone(), dir.size))
+            .collect::<Vec<_>>();
+        directories.sort();
 
-        // "beautiful sunset" has one .xpm file:
         assert_eq!(
-            map["beautiful sunset"]
+            directories,
+            [
+                ("OtherTheme".into(), 1),
+                ("TestTheme".into(), 16),
+                ("TestTheme".into(), 16),
+                ("TestTheme".into(), 32),
+                ("TestTheme".into(), 64),
+                ("TestTheme".into(), 128),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_icon_for_scale() {
+        let base_dir = test_dir("fractional-scale");
+        write_theme(
+            &base_dir,
+            "Scaled",
+            "[Icon Theme]\nName=Scaled\nDirectories=16x16\nScaledDirectories=16x16@2\n\n\
+             [16x16]\nSize=16\n\n\
+             [16x16@2]\nSize=16\nScale=2\n",
+        );
+        for dir in ["16x16", "16x16@2"] {
+            let dir = base_dir.join("Scaled").join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("app.png"), "").unwrap();
+        }
+
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+
+        assert_eq!(icons.nearest_scale(1.0, "Scaled"), 1);
+        assert_eq!(icons.nearest_scale(1.25, "Scaled"), 1);
+        assert_eq!(icons.nearest_scale(1.5, "Scaled"), 2);
+        assert_eq!(icons.nearest_scale(3.0, "Scaled"), 2);
+        assert_eq!(icons.nearest_scale(f32::NAN, "Scaled"), 1);
+
+        let icon = icons.find_icon_for_scale("app", 16, 1.5, "Scaled").unwrap();
+        assert!(icon.path().unwrap().ends_with("16x16@2/app.png"));
+    }
+
+    #[test]
+    fn test_find_icon_with_profile() {
+        let icons = test_search().search().icons();
+
+        let (icon, profile) = icons.find_icon_with_profile("happy", 16, 1, "TestTheme");
+        assert!(icon.is_some());
+        assert_eq!(profile.outcome, LookupOutcome::Exact);
+        assert_eq!(profile.themes_checked, 1);
+        assert_eq!(profile.directories_checked, 1);
+
+        // "pixel" is only found after checking all of TestTheme's directories
+        let (icon, profile) = icons.find_icon_with_profile("pixel", 1, 1, "TestTheme");
+        assert!(icon.is_some());
+        assert_eq!(profile.themes_checked, 2);
+        assert_eq!(profile.directories_checked, 5 + 1);
+
+        let (icon, profile) = icons.find_icon_with_profile("missing", 16, 1, "TestTheme");
+        assert!(icon.is_none());
+        assert_eq!(profile.outcome, LookupOutcome::Miss);
+        assert_eq!(profile.themes_checked, 2);
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let icons = test_search().search().icons();
+        assert!(!icons.is_empty());
+        assert_eq!(icons.theme_count(), 2);
+
+        let icons = IconSearch::new_empty().search().icons();
+        assert!(icons.is_empty());
+        assert_eq!(icons.theme_count(), 0);
+    }
+
+    #[test]
+    fn test_profiler() {
+        let mut icons = test_search().search().icons();
+        let profiles = Arc::new(Mutex::new(Vec::new()));
+
+        let profiles_clone = profiles.clone();
+        icons.set_profiler(move |profile| profiles_clone.lock().unwrap().push(profile));
+
+        icons.find_icon("happy", 16, 1, "TestTheme");
+        icons.find_icon("happy", 64, 1, "TestTheme");
+        icons.find_icon("does-not-exist", 16, 1, "TestTheme");
+        icons.find_icon_with_defaults("happy", 16, 1, "TestTheme");
+        icons.best_icon("happy", 16, 1);
+        icons.find_icon_deadline(
+            "happy",
+            16,
+            1,
+            "TestTheme",
+            Instant::now() + Duration::from_secs(60),
+        );
+
+        let profiles = profiles.lock().unwrap();
+        let outcomes = profiles.iter().map(|p| p.outcome).collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            [
+                LookupOutcome::Exact,
+                LookupOutcome::Closest,
+                LookupOutcome::Miss,
+                LookupOutcome::Exact,
+                LookupOutcome::Exact,
+                LookupOutcome::Exact
+            ]
+        );
+        assert!(
+            profiles
                 .iter()
-                .filter(|ico| ico.file_type() == crate::FileType::Xpm)
-                .count(),
-            1
+                .all(|p| p.files_checked >= p.directories_checked)
+        );
+    }
+
+    #[test]
+    fn test_refresh_theme() {
+        let base_dir = test_dir("refresh-theme");
+        let base_index = "[Icon Theme]\nName=Base\nDirectories=16x16\n\n[16x16]\nSize=16\n";
+        write_theme(&base_dir, "Base", base_index);
+        write_theme(
+            &base_dir,
+            "Child",
+            "[Icon Theme]\nName=Child\nInherits=Base\nDirectories=16x16\n\n[16x16]\nSize=16\n",
+        );
+        write_theme(
+            &base_dir,
+            "GrandChild",
+            "[Icon Theme]\nName=GrandChild\nInherits=Child\nDirectories=16x16\n\n[16x16]\nSize=16\n",
+        );
+        write_theme(&base_dir, "Unrelated", base_index);
+
+        let mut icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        let unrelated = icons.theme("Unrelated").unwrap();
+
+        // the icon is added to `Base` in a directory its index doesn't list yet
+        let icon_dir = base_dir.join("Base/32x32");
+        std::fs::create_dir_all(&icon_dir).unwrap();
+        std::fs::write(icon_dir.join("new.png"), "").unwrap();
+        assert!(icons.find_icon("new", 32, 1, "GrandChild").is_none());
+
+        write_theme(
+            &base_dir,
+            "Base",
+            "[Icon Theme]\nName=Base\nDirectories=16x16,32x32\n\n\
+             [16x16]\nSize=16\n\n[32x32]\nSize=32\n",
+        );
+        icons.refresh_theme("Base").unwrap();
+
+        for theme in ["Base", "Child", "GrandChild"] {
+            let icon = icons.find_icon("new", 32, 1, theme).unwrap();
+            assert_eq!(icon.path().unwrap(), icon_dir.join("new.png"));
+        }
+
+        let base = icons.theme("Base").unwrap();
+        let grand_child = icons.theme("GrandChild").unwrap();
+        assert!(Arc::ptr_eq(
+            &grand_child.inherits_from[0],
+            &icons.theme("Child").unwrap()
+        ));
+        assert!(Arc::ptr_eq(&grand_child.inherits_from[1], &base));
+        assert!(Arc::ptr_eq(&icons.theme("Unrelated").unwrap(), &unrelated));
+
+        assert!(icons.refresh_theme("Missing").is_err());
+    }
+
+    #[test]
+    fn test_refresh_theme_cycle() {
+        let base_dir = test_dir("refresh-theme-cycle");
+        for (name, inherits) in [("A", "B"), ("B", "A"), ("C", "A")] {
+            write_theme(
+                &base_dir,
+                name,
+                &format!("[Icon Theme]\nName={name}\nInherits={inherits}\nDirectories=\n"),
+            );
+        }
+
+        let mut icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        let chains = |icons: &Icons| ["A", "B", "C"].map(|name| icons.theme_chain(name).unwrap());
+        let resolved = chains(&icons);
+
+        for name in ["A", "B", "C"] {
+            icons.refresh_theme(name).unwrap();
+            // the cycle is broken where resolving broke it
+            assert_eq!(chains(&icons), resolved);
+        }
+
+        icons.refresh_theme("A").unwrap();
+        let (a, b, c) = (
+            icons.theme("A").unwrap(),
+            icons.theme("B").unwrap(),
+            icons.theme("C").unwrap(),
         );
+        assert!(Arc::ptr_eq(&a.inherits_from[0], &b));
+        assert!(Arc::ptr_eq(&c.inherits_from[0], &b));
+        assert!(Arc::ptr_eq(&c.inherits_from[1], &a));
     }
 }
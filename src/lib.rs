@@ -24,7 +24,11 @@
 //! By default, **no features** are enabled.
 //!
 //! - **`log`**: Enable logging, which introduces a dependency on the `log` crate.
-//! - **`cache`**: Enables the caching versions of [`Icons`] and [`Theme`] ([`IconsCache`] and [`ThemeCache`]), which introduces a dependency on `qp-trie`.
+//! - **`cache`**: Enables the caching versions of [`Icons`] and [`Theme`] (`IconsCache` and `ThemeCache`), which introduces a dependency on `qp-trie`.
+//! - **`desktop`**: Enables resolving the `Icon` key of desktop entries with `Icons::resolve_desktop_icon` and `Icons::resolve_entries`.
+//! - **`embedded-fallback`**: Embeds a few common icons in the program, to fall back to when no icon theme is installed. See `Icons::with_embedded_fallback`.
+//! - **`render`**: Enables decoding and rasterizing icons to pixels with `IconFile::render_to_size`, which introduces a dependency on `resvg` and `image`.
+//! - **`serde`**: Makes `CacheSnapshot` (de)serializable with `serde`, to persist `ThemeCache`s. Only useful together with the `cache` feature.
 //!
 //! # Icon matching
 //!
@@ -93,13 +97,60 @@
 
 #[cfg(feature = "cache")]
 mod cache;
+mod data;
+#[cfg(feature = "desktop")]
+mod desktop;
+#[cfg(feature = "embedded-fallback")]
+mod embedded;
 mod icon;
+mod profile;
+mod provider;
+#[cfg(feature = "render")]
+mod render;
 mod search;
 mod theme;
 
 #[cfg(feature = "cache")]
 #[cfg_attr(docsrs, doc(cfg(feature = "cache")))]
 pub use cache::*;
+pub use data::*;
 pub use icon::*;
+pub use profile::*;
+pub use provider::*;
 pub use search::*;
 pub use theme::*;
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    /// Resolved icons and themes are meant to be shared between threads, e.g. behind an `Arc`.
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<Icons>();
+        assert_send_sync::<Theme>();
+        assert_send_sync::<ThemeInfo>();
+        assert_send_sync::<ThemeIndex>();
+        assert_send_sync::<DirectoryIndex>();
+        assert_send_sync::<IconFile>();
+        assert_send_sync::<IconData>();
+        assert_send_sync::<IconLocations>();
+        assert_send_sync::<IconSearch>();
+        assert_send_sync::<IconSearch<states::LocationsFound>>();
+        assert_send_sync::<StdFileProvider>();
+        assert_send_sync::<LookupProfile>();
+        assert_send_sync::<ScanStats>();
+        assert_send_sync::<ThemeParseError>();
+        assert_send_sync::<IconDataParseError>();
+        assert_send_sync::<FindError>();
+
+        #[cfg(feature = "cache")]
+        {
+            assert_send_sync::<IconsCache>();
+            assert_send_sync::<ThemeCache>();
+            assert_send_sync::<CacheSnapshot>();
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Measurements taken during a single icon lookup.
+///
+/// These are passed to the profiler installed with [`Icons::set_profiler`](crate::Icons::set_profiler)
+/// after each call to [`Icons::find_icon`](crate::Icons::find_icon), and are returned by
+/// [`Icons::find_icon_with_profile`](crate::Icons::find_icon_with_profile).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LookupProfile {
+    /// The number of themes that were checked for the icon, including the requested theme.
+    pub themes_checked: usize,
+    /// The number of theme directories that were checked for the icon.
+    ///
+    /// A theme directory that is split up over multiple base directories counts once.
+    pub directories_checked: usize,
+    /// The number of file system calls (checks whether a file exists) made during the lookup.
+    pub files_checked: usize,
+    /// How the lookup ended.
+    pub outcome: LookupOutcome,
+    /// Wall-clock time the lookup took.
+    pub duration: Duration,
+}
+
+/// The outcome of an icon lookup, see [`LookupProfile`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum LookupOutcome {
+    /// An icon was found in a directory matching the requested size and scale exactly.
+    Exact,
+    /// No exact match existed, so the icon with the closest size was picked instead.
+    Closest,
+    /// The icon wasn't found in any theme, but a standalone icon by the same name was.
+    Standalone,
+    /// No icon was found.
+    #[default]
+    Miss,
+}
+
+/// Counts of file system operations performed while searching for icons and resolving themes.
+///
+/// Obtain these from [`Icons::scan_stats`](crate::Icons::scan_stats) or
+/// [`IconLocations::scan_stats`](crate::IconLocations::scan_stats).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    /// The number of directories listed.
+    pub read_dir_calls: usize,
+    /// The number of times file metadata was queried, e.g. to check whether a file exists.
+    pub stat_calls: usize,
+    /// The number of files read, i.e. `index.theme` files.
+    pub file_reads: usize,
+}
+
+/// Thread-safe counterpart of [`ScanStats`], which is incremented during scanning.
+#[derive(Debug, Default)]
+pub(crate) struct ScanCounters {
+    read_dir_calls: AtomicUsize,
+    stat_calls: AtomicUsize,
+    file_reads: AtomicUsize,
+}
+
+impl ScanCounters {
+    pub(crate) fn count_read_dir(&self) {
+        self.read_dir_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_stat(&self) {
+        self.stat_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_file_read(&self) {
+        self.file_reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add(&self, stats: ScanStats) {
+        self.read_dir_calls
+            .fetch_add(stats.read_dir_calls, Ordering::Relaxed);
+        self.stat_calls
+            .fetch_add(stats.stat_calls, Ordering::Relaxed);
+        self.file_reads
+            .fetch_add(stats.file_reads, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ScanStats {
+        ScanStats {
+            read_dir_calls: self.read_dir_calls.load(Ordering::Relaxed),
+            stat_calls: self.stat_calls.load(Ordering::Relaxed),
+            file_reads: self.file_reads.load(Ordering::Relaxed),
+        }
+    }
+}
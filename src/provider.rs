@@ -0,0 +1,147 @@
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Abstraction over the file system operations performed while searching for icons and themes, and
+/// while looking up icons.
+///
+/// By default, [`StdFileProvider`] is used, which accesses the file system through [`std::fs`].
+/// Implement this trait to search for icons elsewhere, for example in a virtual file system or in
+/// memory for testing, and pass it to [`IconSearch::with_file_provider`](crate::IconSearch::with_file_provider).
+///
+/// Paths passed to the provider are derived from the directories the [`IconSearch`](crate::IconSearch)
+/// was configured with.
+pub trait FileProvider: Send + Sync {
+    /// Checks whether a file or directory exists at `path`.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Checks whether a directory (or a symlink leading to one) exists at `path`.
+    ///
+    /// The default implementation checks whether `path` can be listed with
+    /// [`read_dir`](FileProvider::read_dir). Override it if that is expensive.
+    fn is_dir(&self, path: &Path) -> bool {
+        self.read_dir(path).is_ok()
+    }
+
+    /// Checks whether a file (or a symlink leading to one) exists at `path`.
+    ///
+    /// The default implementation checks whether something exists at `path` that isn't a
+    /// [directory](FileProvider::is_dir).
+    fn is_file(&self, path: &Path) -> bool {
+        self.exists(path) && !self.is_dir(path)
+    }
+
+    /// Checks whether a symlink is at `path`, regardless of whether it leads anywhere.
+    ///
+    /// This is only used to report dangling symlinks. The default implementation returns `false`.
+    fn is_symlink(&self, path: &Path) -> bool {
+        let _ = path;
+        false
+    }
+
+    /// Resolves `path` to the path of the file or directory it leads to, following symlinks.
+    ///
+    /// This is used to [deduplicate](crate::IconSearch::dedup_theme_directories) theme
+    /// directories. The default implementation returns `path` as-is: only paths that are equal are
+    /// considered to lead to the same directory.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_owned())
+    }
+
+    /// Lists the entries of the directory at `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FileEntry>>;
+
+    /// Reads the entire contents of the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Returns the contents of the file at `path` if they are embedded in the program, rather than
+    /// stored on disk.
+    ///
+    /// Icons found at such paths hold their contents in memory, see [`IconFile::bytes`](crate::IconFile::bytes).
+    /// The default implementation returns `None`: all files are read from disk.
+    fn embedded_contents(&self, path: &Path) -> Option<&'static [u8]> {
+        let _ = path;
+        None
+    }
+
+    /// Returns when the file or directory at `path` was last modified, if that can be determined.
+    ///
+    /// This is used by [`Icons::needs_refresh`](crate::Icons::needs_refresh) to detect changes.
+    /// The default implementation returns `None`, so changes are never detected.
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        let _ = path;
+        None
+    }
+}
+
+impl Debug for dyn FileProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FileProvider")
+    }
+}
+
+/// An entry of a directory, as listed by [`FileProvider::read_dir`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileEntry {
+    /// The full path of this entry, i.e. the path of the directory joined with the entry's name.
+    pub path: PathBuf,
+    /// Whether this entry is a directory (or leads to one).
+    pub is_dir: bool,
+}
+
+/// The default [`FileProvider`], accessing the file system through [`std::fs`].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct StdFileProvider;
+
+impl FileProvider for StdFileProvider {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        path.is_symlink()
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FileEntry>> {
+        let entries = path
+            .read_dir()?
+            .flatten() // skip entries that failed to read
+            .filter_map(|entry| {
+                let file_type = entry.file_type().ok()?;
+                let path = entry.path();
+
+                // symlinks without an extension are assumed to lead to directories, which saves
+                // us from following every symlink found
+                let is_dir =
+                    file_type.is_dir() || (file_type.is_symlink() && path.extension().is_none());
+
+                Some(FileEntry { path, is_dir })
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        path.metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+}
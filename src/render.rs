@@ -0,0 +1,274 @@
+use crate::{FileType, IconFile};
+use image::RgbaImage;
+use resvg::{tiny_skia, usvg};
+use std::collections::HashMap;
+
+impl IconFile {
+    /// Decode this icon into pixels.
+    ///
+    /// - Scalable icons ([FileType::Svg]) are rasterized at exactly `px`×`px` pixels. Images that
+    ///   aren't square are scaled to fit, and centered. Gzip-compressed SVGs (`.svgz`) are
+    ///   decompressed first.
+    /// - PNG and XPM icons ([FileType::Png], [FileType::Xpm]) are decoded as-is, and are **not**
+    ///   resized to `px`. Their size can be inspected on the returned image. XPM colors must be
+    ///   given as hexadecimal values, `None`, or one of a few basic color names like `black`.
+    ///
+    /// Returns `None` if the file couldn't be read or decoded, or if `px` is 0.
+    #[cfg_attr(docsrs, doc(cfg(feature = "render")))]
+    pub fn render_to_size(&self, px: u32) -> Option<RgbaImage> {
+        if px == 0 {
+            return None;
+        }
+
+        match self.file_type() {
+            FileType::Svg => render_svg(&self.read().ok()?, px),
+            FileType::Png => {
+                let image = match self.path() {
+                    Some(path) => image::open(path),
+                    None => image::load_from_memory(&self.read().ok()?),
+                };
+                Some(image.ok()?.into_rgba8())
+            }
+            FileType::Xpm => decode_xpm(&self.read().ok()?),
+        }
+    }
+}
+
+/// Decodes an XPM (version 3) image: a C array of strings holding its dimensions, colors and
+/// pixels.
+fn decode_xpm(data: &[u8]) -> Option<RgbaImage> {
+    let text = std::str::from_utf8(data).ok()?;
+    let mut strings = xpm_strings(text);
+
+    let mut values = strings.next()?.split_whitespace().map(str::parse::<usize>);
+    let (width, height, colors, chars_per_pixel) = (
+        values.next()?.ok()?,
+        values.next()?.ok()?,
+        values.next()?.ok()?,
+        values.next()?.ok()?,
+    );
+    if chars_per_pixel == 0 {
+        return None;
+    }
+
+    let mut palette = HashMap::with_capacity(colors);
+    for _ in 0..colors {
+        let line = strings.next()?;
+        let key = line.get(..chars_per_pixel)?;
+        palette.insert(key, xpm_color(line.get(chars_per_pixel..)?)?);
+    }
+
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for _ in 0..height {
+        let row = strings.next()?.as_bytes();
+        if row.len() < width * chars_per_pixel {
+            return None;
+        }
+
+        for key in row.chunks_exact(chars_per_pixel).take(width) {
+            let key = std::str::from_utf8(key).ok()?;
+            pixels.extend(palette.get(key)?);
+        }
+    }
+
+    RgbaImage::from_raw(width as u32, height as u32, pixels)
+}
+
+/// The contents of the string literals of an XPM file, skipping comments.
+fn xpm_strings(mut text: &str) -> impl Iterator<Item = &str> {
+    std::iter::from_fn(move || {
+        loop {
+            let start = text.find(['"', '/'])?;
+            text = &text[start..];
+
+            if let Some(comment) = text.strip_prefix("/*") {
+                text = &comment[comment.find("*/")? + 2..];
+            } else if let Some(string) = text.strip_prefix('"') {
+                let end = string.find('"')?;
+                text = &string[end + 1..];
+                return Some(&string[..end]);
+            } else {
+                text = &text[1..];
+            }
+        }
+    })
+}
+
+/// Parses the color of an XPM color line, after its key: pairs of a context and a color, of which
+/// the color context (`c`) is preferred, followed by the grayscale and monochrome ones.
+fn xpm_color(spec: &str) -> Option<[u8; 4]> {
+    const CONTEXTS: [&str; 5] = ["c", "g", "g4", "m", "s"];
+
+    let mut colors: Vec<(&str, String)> = Vec::new();
+    for word in spec.split_whitespace() {
+        match colors.last_mut() {
+            // colors may consist of multiple words, like `light grey`
+            Some((_, color)) if !CONTEXTS.contains(&word) => {
+                if !color.is_empty() {
+                    color.push(' ');
+                }
+                color.push_str(word);
+            }
+            _ if CONTEXTS.contains(&word) => colors.push((word, String::new())),
+            _ => return None,
+        }
+    }
+
+    let (_, color) = ["c", "g", "g4", "m"]
+        .iter()
+        .find_map(|context| colors.iter().find(|(found, _)| found == context))?;
+
+    if let Some(hex) = color.strip_prefix('#') {
+        // 4, 8 or 16 bits per channel, of which the most significant 8 are used
+        let digits = hex.len() / 3;
+        if hex.len() % 3 != 0 || !(1..=4).contains(&digits) {
+            return None;
+        }
+
+        let channel = |i: usize| {
+            let value = u16::from_str_radix(hex.get(i * digits..(i + 1) * digits)?, 16).ok()?;
+            let value = if digits == 1 {
+                value * 0x11
+            } else {
+                value >> ((digits - 2) * 4)
+            };
+            Some(value as u8)
+        };
+        return Some([channel(0)?, channel(1)?, channel(2)?, 255]);
+    }
+
+    let rgb = match color.to_ascii_lowercase().as_str() {
+        "none" => return Some([0, 0, 0, 0]),
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "green" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" => [0, 255, 255],
+        "magenta" => [255, 0, 255],
+        "gray" | "grey" => [190, 190, 190],
+        _ => return None,
+    };
+    Some([rgb[0], rgb[1], rgb[2], 255])
+}
+
+fn render_svg(data: &[u8], px: u32) -> Option<RgbaImage> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default()).ok()?;
+
+    let size = tree.size();
+    let scale = (px as f32 / size.width()).min(px as f32 / size.height());
+    let offset_x = (px as f32 - size.width() * scale) / 2.0;
+    let offset_y = (px as f32 - size.height() * scale) / 2.0;
+
+    let mut pixmap = tiny_skia::Pixmap::new(px, px)?;
+    let transform =
+        tiny_skia::Transform::from_scale(scale, scale).post_translate(offset_x, offset_y);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia works with premultiplied alpha, `image` does not.
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+
+    RgbaImage::from_raw(px, px, pixels)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::search::test::{PROJ_ROOT, test_search};
+    use crate::{FileType, IconFile};
+    use std::path::Path;
+
+    #[test]
+    fn test_render_png() {
+        let icons = test_search().search().icons();
+        let icon = icons.find_icon("happy", 16, 1, "TestTheme").unwrap();
+
+        let image = icon.render_to_size(64).unwrap();
+        assert_eq!(image.dimensions(), (16, 16), "raster icons aren't resized");
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+            <rect width="16" height="16" fill="red"/>
+        </svg>"#;
+
+        let image = super::render_svg(svg, 48).unwrap();
+        assert_eq!(image.dimensions(), (48, 48));
+        assert_eq!(image.get_pixel(24, 24).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_render_svgz() {
+        let icon = IconFile::from_parts(
+            Path::new(PROJ_ROOT).join("resources/example.svgz"),
+            FileType::Svg,
+        );
+
+        let image = icon.render_to_size(32).unwrap();
+        assert_eq!(image.dimensions(), (32, 32));
+        assert_eq!(image.get_pixel(16, 16).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_render_xpm() {
+        let icon = IconFile::from_path_buf(Path::new(PROJ_ROOT).join(
+            "resources/test_icons_alt/TestTheme/UnconventionalDirectoryName/γ/beautiful sunset.xpm",
+        ))
+        .unwrap();
+        assert_eq!(icon.file_type(), FileType::Xpm);
+
+        let image = icon.render_to_size(16).unwrap();
+        assert_eq!(image.dimensions(), (64, 64), "raster icons aren't resized");
+        assert_eq!(image.get_pixel(0, 0).0, [0x82, 0xE5, 0xFB, 255]);
+    }
+
+    #[test]
+    fn test_decode_xpm() {
+        let xpm = br#"/* XPM */
+            static char *test[] = {
+            /* columns rows colors chars-per-pixel */
+            "3 2 3 2",
+            "  c None",
+            ".. c #F00 m black",
+            "XX s name c grey",
+            "  ..XX",
+            "XX..  "
+            };"#;
+
+        let image = super::decode_xpm(xpm).unwrap();
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(image.get_pixel(1, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [190, 190, 190, 255]);
+
+        assert_eq!(
+            super::xpm_color("c #FFFF00008000"),
+            Some([255, 0, 128, 255])
+        );
+        assert_eq!(super::xpm_color("m white"), Some([255, 255, 255, 255]));
+        // unknown color names can't be decoded
+        assert_eq!(super::xpm_color("c light grey"), None);
+        assert!(super::decode_xpm(b"\"1 1 1 1\", \". c #000\", \"x\"").is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-fallback")]
+    fn test_render_embedded() {
+        let icons = crate::IconSearch::new_empty()
+            .search()
+            .icons()
+            .with_embedded_fallback();
+        let icon = icons.find_default_icon("folder", 32, 1).unwrap();
+
+        let image = icon.render_to_size(32).unwrap();
+        assert_eq!(image.dimensions(), (32, 32));
+    }
+}
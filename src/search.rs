@@ -1,11 +1,15 @@
-use crate::icon::IconFile;
-use crate::{Icons, Theme, ThemeInfo, ThemeParseError};
+use crate::icon::{IconFile, OtherIconFile};
+use crate::profile::ScanCounters;
+use crate::provider::{FileProvider, StdFileProvider};
+use crate::{Icons, ScanStats, Theme, ThemeIndex, ThemeInfo, ThemeParseError};
 use states::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
+use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 macro_rules! states {
     ($($(#[$($attr:tt)*])* $id:ident),*) => {
@@ -64,6 +68,7 @@ pub mod states {
 /// To add directories to the instance, use [`IconSearch::add_directories`].
 ///
 /// To construct a new `IconSearch` from a list, use the `From` implementation or [`IconSearch::new_from`].
+/// To search just a single directory, use [`IconSearch::new_single`].
 ///
 /// # Example
 ///
@@ -82,6 +87,7 @@ pub mod states {
 pub struct IconSearch<State = Initial> {
     /// The list of directories to search for standalone icons and icon themes
     pub dirs: Vec<PathBuf>,
+    options: SearchOptions,
     icon_locations: Option<IconLocations>,
     icons: Option<Icons>,
     // in fn() so that the compiler doesn't see State as part of this struct,
@@ -89,6 +95,31 @@ pub struct IconSearch<State = Initial> {
     _state: PhantomData<fn() -> State>,
 }
 
+/// Configuration of an [`IconSearch`], set through its builder methods.
+#[derive(Clone)]
+struct SearchOptions {
+    dedup_theme_dirs: bool,
+    /// `None` means [`StdFileProvider`].
+    file_provider: Option<Arc<dyn FileProvider>>,
+    /// Lowercase extensions of extra file types accepted as standalone icons.
+    standalone_extensions: Vec<String>,
+    inheritance_order: InheritanceOrder,
+    /// Internal names of themes to leave out of the search.
+    excluded_themes: Vec<OsString>,
+}
+
+impl SearchOptions {
+    const fn new() -> Self {
+        Self {
+            dedup_theme_dirs: false,
+            file_provider: None,
+            standalone_extensions: Vec::new(),
+            inheritance_order: InheritanceOrder::Bfs,
+            excluded_themes: Vec::new(),
+        }
+    }
+}
+
 impl IconSearch<Initial> {
     // -- STAGE 1: Establish directories wherein to find icons
 
@@ -98,6 +129,18 @@ impl IconSearch<Initial> {
     /// - `/usr/share/pixmaps`
     ///
     /// If you wish to add directories to those, use this function and then [`add_directories`](Self::add_directories).
+    ///
+    /// # `ICON_SEARCH_PATH`
+    ///
+    /// If the `ICON_SEARCH_PATH` environment variable is set, its directories *replace* the
+    /// default directories above. Like `PATH`, it is a list of directories separated by the
+    /// platform's path separator (`:`, or `;` on Windows), in order of precedence. Empty entries are ignored, and if no directories remain (for example
+    /// if the variable is set but empty), the default directories are used.
+    ///
+    /// This allows overriding where icons are looked up, for example for debugging or in a
+    /// container, without code changes. Directories added with
+    /// [`add_directories`](Self::add_directories) are still added after those of the variable.
+    /// [`new_from`](Self::new_from) and [`new_empty`](Self::new_empty) never read it.
     pub fn new() -> Self {
         <Self as Default>::default()
     }
@@ -111,12 +154,26 @@ impl IconSearch<Initial> {
     pub const fn new_from(dirs: Vec<PathBuf>) -> Self {
         Self {
             dirs,
+            options: SearchOptions::new(),
             icon_locations: None,
             icons: None,
             _state: PhantomData,
         }
     }
 
+    /// Constructs a new `IconSearch` that searches a single directory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use icon::IconSearch;
+    ///
+    /// let icons = IconSearch::new_single("/opt/my-app/icons").search().icons();
+    /// ```
+    pub fn new_single(dir: impl Into<PathBuf>) -> Self {
+        Self::new_from(vec![dir.into()])
+    }
+
     /// Adds a list of directories to this `IconSearch`.
     ///
     /// # Example
@@ -140,27 +197,153 @@ impl IconSearch<Initial> {
         self
     }
 
+    /// Sets whether directories of the same theme that are actually the same directory on disk
+    /// should be deduplicated. This is disabled by default.
+    ///
+    /// A theme directory may be reachable through multiple base directories, for example when it
+    /// is symlinked into `~/.icons`, or when a base directory is listed twice. Without
+    /// deduplication, lookups will check such directories once for each path they're reachable
+    /// through.
+    ///
+    /// Enabling this resolves the path of every theme directory found with
+    /// [`FileProvider::canonicalize`], and only keeps the first path leading to each physical
+    /// directory. The kept paths themselves are not changed.
+    pub fn dedup_theme_directories(mut self, dedup: bool) -> Self {
+        self.options.dedup_theme_dirs = dedup;
+
+        self
+    }
+
+    /// Sets the [`FileProvider`] used to access the file system, both while searching and during
+    /// icon lookups with the resulting [`Icons`]. By default, [`StdFileProvider`] is used.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use icon::{IconSearch, StdFileProvider};
+    ///
+    /// let icons = IconSearch::new()
+    ///     .with_file_provider(StdFileProvider)
+    ///     .search()
+    ///     .icons();
+    /// ```
+    pub fn with_file_provider(mut self, file_provider: impl FileProvider + 'static) -> Self {
+        self.options.file_provider = Some(Arc::new(file_provider));
+
+        self
+    }
+
+    /// Sets extra file extensions (e.g. `"ico"` or `"gif"`) of files to accept as standalone icons,
+    /// besides the `png`, `xpm` and `svg` files that are always accepted. Extensions are matched
+    /// ignoring case.
+    ///
+    /// Some directories of standalone icons, like `/usr/share/pixmaps`, historically contain icons
+    /// of other formats. These are found with [`Icons::find_other_standalone_icon`].
+    /// Icons in icon themes are not affected: those are always restricted to the formats allowed
+    /// by the Icon Theme specification.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use icon::IconSearch;
+    ///
+    /// let icons = IconSearch::new()
+    ///     .standalone_extensions(&["ico", "gif"])
+    ///     .search()
+    ///     .icons();
+    /// ```
+    pub fn standalone_extensions(mut self, extensions: &[&str]) -> Self {
+        self.options.standalone_extensions = extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+
+        self
+    }
+
+    /// Sets the order in which the (transitive) parents of each theme are searched for icons.
+    /// See [`InheritanceOrder`]; the default is [`InheritanceOrder::Bfs`].
+    pub fn inheritance_order(mut self, order: InheritanceOrder) -> Self {
+        self.options.inheritance_order = order;
+
+        self
+    }
+
+    /// Leaves the themes with these internal names (their directory names, like `"Adwaita"`) out
+    /// of the search, as if they weren't installed.
+    ///
+    /// Use this to skip themes that are broken or slow to resolve. Exclusion is absolute: the
+    /// themes are never parsed, and they are not used as parents either. Themes that inherit from
+    /// an excluded theme skip it, just like they would skip a parent that isn't installed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use icon::IconSearch;
+    ///
+    /// let icons = IconSearch::new()
+    ///     .exclude_themes(&["BrokenVendorTheme"])
+    ///     .search()
+    ///     .icons();
+    /// assert!(icons.theme("BrokenVendorTheme").is_none());
+    /// ```
+    pub fn exclude_themes(mut self, themes: &[&str]) -> Self {
+        self.options
+            .excluded_themes
+            .extend(themes.iter().map(OsString::from));
+
+        self
+    }
+
     // -- STAGE 2: In search dirs, find standalone icons and directories that may be icon themes
 
     fn find_icon_locations(&self) -> IconLocations {
         // "Each theme is stored as subdirectories of the base directories"
 
+        let scan_counters = ScanCounters::default();
+        let file_provider = self
+            .options
+            .file_provider
+            .clone()
+            .unwrap_or_else(|| Arc::new(StdFileProvider));
+
         let (dirs, files) = self
             .dirs
             .iter()
-            .flat_map(|base_dir| base_dir.read_dir()) // read the entries in each base dir
-            .flatten() // merge all the iterators
-            .flatten() // remove Err entries
-            .filter_map(|entry| Some((entry.file_type().ok()?, entry))) // get file type for each entry and skip if fail
-            .partition::<Vec<_>, _>(|(ft, entry)| {
-                ft.is_dir() || (entry.path().extension().is_none() && ft.is_symlink())
-            });
+            // search directories may be misconfigured, e.g. `$XDG_DATA_DIRS` entries that point
+            // at files: skip anything that isn't a directory before trying to list it.
+            .filter(|base_dir| {
+                scan_counters.count_stat();
+                if file_provider.is_dir(base_dir) {
+                    return true;
+                }
+
+                #[cfg(feature = "log")]
+                if file_provider.exists(base_dir) {
+                    log::warn!("skipping search directory {base_dir:?}, as it isn't a directory");
+                }
+
+                false
+            })
+            .inspect(|_| scan_counters.count_read_dir())
+            .flat_map(|base_dir| file_provider.read_dir(base_dir)) // read the entries in each base dir
+            .flatten() // merge all the entries
+            .partition::<Vec<_>, _>(|entry| entry.is_dir);
 
         // icons at the top-level in a base_dir don't belong to a theme, but must still be able to be found!
-        let files = files
-            .into_iter()
-            .flat_map(|(_, entry)| IconFile::from_path(&entry.path()))
-            .collect::<Vec<_>>();
+        let mut icon_files = Vec::new();
+        let mut other_files = Vec::new();
+        for entry in files {
+            if let Some(file) = IconFile::from_path(&entry.path) {
+                icon_files.push(file);
+            } else if let Some(file) = self.other_icon_file(entry.path) {
+                other_files.push(file);
+            }
+        }
+
+        // prefer extensions in the order they were configured in
+        let extensions = &self.options.standalone_extensions;
+        other_files.sort_by_key(|file| extensions.iter().position(|ext| ext == file.extension()));
 
         // "In at least one of the theme directories there must be a file called
         // index.theme that describes the theme. The first index.theme found while
@@ -168,21 +351,61 @@ impl IconSearch<Initial> {
 
         // For each theme name, create a list of directories where it may be found:
         let mut themes_directories: HashMap<OsString, Vec<PathBuf>> = HashMap::new();
-        for (_, dir) in dirs {
-            let theme_name = dir.file_name();
+        for dir in dirs {
+            let Some(theme_name) = dir.path.file_name() else {
+                continue;
+            };
+            if self
+                .options
+                .excluded_themes
+                .iter()
+                .any(|name| name == theme_name)
+            {
+                continue;
+            }
 
             themes_directories
-                .entry(theme_name)
+                .entry(theme_name.to_os_string())
                 .or_default()
-                .push(dir.path());
+                .push(dir.path);
+        }
+
+        if self.options.dedup_theme_dirs {
+            for dirs in themes_directories.values_mut() {
+                let mut seen = HashSet::new();
+
+                // directories that can't be resolved are kept, they'll just be skipped during lookups
+                dirs.retain(|dir| {
+                    scan_counters.count_stat();
+                    file_provider
+                        .canonicalize(dir)
+                        .map_or(true, |dir| seen.insert(dir))
+                });
+            }
         }
 
         IconLocations {
-            standalone_icons: files,
+            standalone_icons: icon_files,
+            other_standalone_icons: other_files,
             themes_directories,
+            scan_counters,
+            file_provider,
+            inheritance_order: self.options.inheritance_order,
         }
     }
 
+    /// Creates an `OtherIconFile` for a file found outside of themes if it has one of the configured
+    /// [standalone extensions](Self::standalone_extensions).
+    fn other_icon_file(&self, path: PathBuf) -> Option<OtherIconFile> {
+        let file = OtherIconFile::from_path_buf(path)?;
+
+        self.options
+            .standalone_extensions
+            .iter()
+            .any(|ext| ext == file.extension())
+            .then_some(file)
+    }
+
     /// Find icons and icon themes in the configured search directories.
     ///
     /// This function proceeds the [`IconSearch`] to the [next stage](LocationsFound).
@@ -191,6 +414,7 @@ impl IconSearch<Initial> {
 
         IconSearch::<LocationsFound> {
             dirs: self.dirs,
+            options: self.options,
             icon_locations: Some(icon_locations),
             icons: None,
             _state: PhantomData,
@@ -217,10 +441,16 @@ impl IconSearch<LocationsFound> {
 
     fn finish(self) -> IconSearch<Finished> {
         let icons = self.icon_locations.expect("guaranteed by type-state");
-        let icons = icons.icons();
+        let mut icons = icons.icons();
+        icons.refresh = Some(RefreshState::capture(
+            self.dirs.clone(),
+            self.options.clone(),
+            &icons,
+        ));
 
         IconSearch {
             dirs: self.dirs,
+            options: self.options,
             icon_locations: None, // consumed!
             icons: Some(icons),
             _state: PhantomData,
@@ -233,6 +463,16 @@ impl IconSearch<LocationsFound> {
         self.finish().icons()
     }
 
+    /// Like [`icons`](IconSearch::icons), but calls `progress` after each icon theme candidate is
+    /// parsed. See [`IconLocations::resolve_with_progress`].
+    pub fn icons_with_progress(self, progress: impl FnMut(Progress)) -> Icons {
+        let (dirs, options) = (self.dirs.clone(), self.options.clone());
+        let mut icons = self.into_icon_locations().icons_with_progress(progress);
+        icons.refresh = Some(RefreshState::capture(dirs, options, &icons));
+
+        icons
+    }
+
     #[cfg(feature = "cache")]
     #[cfg_attr(docsrs, doc(cfg(feature = "cache")))]
     /// Like [`icons`](IconSearch::icons), but immediately wrapped into the cached type.
@@ -265,15 +505,202 @@ impl IconSearch<Finished> {
 /// - A list of standalone icons. These are "loose" icons found in the searched base directories. They do not belong to any theme.
 /// - A map of icon theme identifiers ("internal name"s) to all directories where that icon theme's icons live.
 ///   This is a list because icon themes may be split up over multiple base directories.
+///
+/// Use [`IconLocations::new`] to construct one by hand.
 #[derive(Debug)]
 pub struct IconLocations {
     /// List of icons not belonging to any theme.
     pub standalone_icons: Vec<IconFile>,
+    /// List of icons not belonging to any theme, in formats accepted through
+    /// [`IconSearch::standalone_extensions`].
+    pub other_standalone_icons: Vec<OtherIconFile>,
     /// Map of icon theme identifiers to the directories where the icons live.
     pub themes_directories: HashMap<OsString, Vec<PathBuf>>,
+    /// File system operations performed to find these locations, and to load themes from them.
+    pub(crate) scan_counters: ScanCounters,
+    /// Used to access the file system, and passed on to the themes resolved from these locations.
+    pub(crate) file_provider: Arc<dyn FileProvider>,
+    /// The order in which the parents of resolved themes are searched.
+    pub(crate) inheritance_order: InheritanceOrder,
+}
+
+/// What is needed to repeat the search an [`Icons`] was built from, see
+/// [`Icons::refresh_if_changed`].
+pub(crate) struct RefreshState {
+    dirs: Vec<PathBuf>,
+    options: SearchOptions,
+    /// The directories searched, the directories and indices of all themes, and when they were
+    /// last modified.
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl RefreshState {
+    /// Captures the modification times of the directories of `icons`, counting them in its
+    /// [`scan_stats`](Icons::scan_stats).
+    fn capture(dirs: Vec<PathBuf>, options: SearchOptions, icons: &Icons) -> Self {
+        let theme_paths = icons.themes.values().flat_map(|theme| {
+            let info = &theme.info;
+            info.base_dirs
+                .iter()
+                .chain(std::iter::once(&info.index_location))
+                .cloned()
+        });
+
+        let modified = dirs
+            .iter()
+            .cloned()
+            .chain(theme_paths)
+            .map(|path| {
+                icons.scan_counters.count_stat();
+                let modified = icons.file_provider.modified(&path);
+                (path, modified)
+            })
+            .collect();
+
+        RefreshState {
+            dirs,
+            options,
+            modified,
+        }
+    }
+
+    /// Checks whether any of the directories of `icons` was modified since this state was
+    /// captured.
+    pub(crate) fn is_outdated(&self, icons: &Icons) -> bool {
+        self.modified.iter().any(|(path, modified)| {
+            icons.scan_counters.count_stat();
+            icons.file_provider.modified(path) != *modified
+        })
+    }
+
+    /// Repeats the search, with the same directories and options.
+    pub(crate) fn search(&self) -> Icons {
+        IconSearch::<Initial> {
+            dirs: self.dirs.clone(),
+            options: self.options.clone(),
+            icon_locations: None,
+            icons: None,
+            _state: PhantomData,
+        }
+        .search()
+        .icons()
+    }
+}
+
+/// The order in which the (transitive) parents of a theme are searched for icons, set with
+/// [`IconSearch::inheritance_order`].
+///
+/// The Icon Theme specification only states that a theme's parents are searched in the order
+/// they're listed in its `Inherits` key, but not how to search the parents of those parents.
+/// Either way, every theme is searched at most once, and `hicolor` is searched last unless a
+/// theme inherits from it explicitly (see [`Dfs`](InheritanceOrder::Dfs)).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum InheritanceOrder {
+    /// Breadth-first: all parents a theme declares are searched before any of their own parents.
+    ///
+    /// As nearly every theme (transitively) inherits from `hicolor`, which has many icons, this
+    /// makes sure that all declared parents get a chance to provide an icon before `hicolor` does.
+    /// A theme's parents are always searched after every theme that inherits from them.
+    #[default]
+    Bfs,
+    /// Depth-first: each declared parent is searched along with all of its own parents, before
+    /// moving on to the next declared parent.
+    ///
+    /// This respects the layering of a theme's declared parents: a fallback listed first is
+    /// entirely searched before the next one. However, if a parent explicitly inherits from
+    /// `hicolor`, `hicolor` is searched before the parents declared after it, which may then
+    /// never provide an icon.
+    Dfs,
+}
+
+/// Computes the lookup order of a theme: the theme itself, followed by its (transitive) parents in
+/// the given order, ending with `hicolor` if it wasn't reached otherwise.
+///
+/// `parents_of` returns the (valid) parents a theme declares.
+pub(crate) fn inheritance_chain<K: Clone + PartialEq>(
+    theme: K,
+    parents_of: impl Fn(&K) -> Vec<K>,
+    hicolor: Option<K>,
+    order: InheritanceOrder,
+) -> Vec<K> {
+    fn visit<K: Clone + PartialEq>(
+        node: &K,
+        parents_of: &impl Fn(&K) -> Vec<K>,
+        chain: &mut Vec<K>,
+    ) {
+        for parent in parents_of(node) {
+            if !chain.contains(&parent) {
+                chain.push(parent.clone());
+                visit(&parent, parents_of, chain);
+            }
+        }
+    }
+
+    let mut chain = vec![theme.clone()];
+
+    match order {
+        InheritanceOrder::Bfs => {
+            let mut cursor = 0;
+            while let Some(node) = chain.get(cursor).cloned() {
+                cursor += 1;
+
+                for parent in parents_of(&node) {
+                    if parent == theme {
+                        continue;
+                    }
+
+                    // add this parent, removing any previous occurrences
+                    chain.retain(|other| *other != parent);
+                    chain.push(parent);
+                }
+            }
+
+            // From the spec: "If no theme is specified, implementations are required to add the
+            //                 "hicolor" theme to the inheritance tree."
+            if let Some(hicolor) = hicolor.filter(|hicolor| *hicolor != theme) {
+                chain.retain(|other| *other != hicolor);
+                chain.push(hicolor);
+            }
+        }
+        InheritanceOrder::Dfs => {
+            visit(&theme, &parents_of, &mut chain);
+
+            if let Some(hicolor) = hicolor.filter(|hicolor| !chain.contains(hicolor)) {
+                chain.push(hicolor);
+            }
+        }
+    }
+
+    chain
+}
+
+/// Progress of resolving icon themes, see [`IconLocations::resolve_with_progress`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of icon theme candidates parsed so far.
+    pub processed: usize,
+    /// The number of icon theme candidates found by the search.
+    pub total: usize,
 }
 
 impl IconLocations {
+    /// Creates `IconLocations` from a list of standalone icons and the directories of each theme.
+    ///
+    /// Themes resolved from these locations access the file system through [`StdFileProvider`].
+    pub fn new(
+        standalone_icons: Vec<IconFile>,
+        themes_directories: HashMap<OsString, Vec<PathBuf>>,
+    ) -> Self {
+        Self {
+            standalone_icons,
+            other_standalone_icons: Vec::new(),
+            themes_directories,
+            scan_counters: ScanCounters::default(),
+            file_provider: Arc::new(StdFileProvider),
+            inheritance_order: InheritanceOrder::default(),
+        }
+    }
+
     /// Find icon locations from a given `IconSearch` (in initial state).
     ///
     /// There are few reasons to use this function.
@@ -289,6 +716,56 @@ impl IconLocations {
         dirs.find_icon_locations()
     }
 
+    /// Merge the locations found by another search into this one.
+    ///
+    /// Standalone icons from `other` are added if they aren't present yet, and the theme directories
+    /// of `other` are appended to the matching themes (creating new entries for themes that weren't
+    /// found before). Directories of `other` are placed _after_ those already present, so the
+    /// `index.theme` of this `IconLocations` keeps precedence. Likewise, the
+    /// [`FileProvider`] of this `IconLocations` is kept.
+    ///
+    /// This allows scanning different roots separately and combining them before resolving.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use icon::IconSearch;
+    ///
+    /// let mut locations = IconSearch::new().search().into_icon_locations();
+    /// let bundled = IconSearch::new_from(vec!["/opt/my-app/icons".into()])
+    ///     .search()
+    ///     .into_icon_locations();
+    ///
+    /// locations.merge(bundled);
+    ///
+    /// let icons = locations.icons();
+    /// ```
+    pub fn merge(&mut self, other: IconLocations) {
+        self.scan_counters.add(other.scan_counters.snapshot());
+
+        for icon in other.standalone_icons {
+            if !self.standalone_icons.contains(&icon) {
+                self.standalone_icons.push(icon);
+            }
+        }
+
+        for icon in other.other_standalone_icons {
+            if !self.other_standalone_icons.contains(&icon) {
+                self.other_standalone_icons.push(icon);
+            }
+        }
+
+        for (theme_name, dirs) in other.themes_directories {
+            let existing = self.themes_directories.entry(theme_name).or_default();
+
+            for dir in dirs {
+                if !existing.contains(&dir) {
+                    existing.push(dir);
+                }
+            }
+        }
+    }
+
     /// Collects all standalone icons, themes, and all the dependencies of the themes found.
     ///
     /// Wraps everything up into the central [`Icons`] struct, which may then be used to perform actual
@@ -296,22 +773,57 @@ impl IconLocations {
     pub fn icons(self) -> Icons {
         let themes = self.resolve();
 
-        let standalone_icons = self
-            .standalone_icons
-            .into_iter()
-            .map(|file| {
-                let key = file
-                    .path()
+        self.into_icons(themes)
+    }
+
+    /// Like [`icons`](IconLocations::icons), but calls `progress` after each icon theme candidate is
+    /// parsed. See [`resolve_with_progress`](IconLocations::resolve_with_progress).
+    pub fn icons_with_progress(self, progress: impl FnMut(Progress)) -> Icons {
+        let themes = self.resolve_with_progress(progress);
+
+        self.into_icons(themes)
+    }
+
+    fn into_icons(self, themes: HashMap<OsString, Arc<Theme>>) -> Icons {
+        let default_theme = self.default_theme();
+        let pointer_themes = self.pointer_themes(&themes);
+
+        let mut standalone_icons: HashMap<String, Vec<IconFile>> = HashMap::new();
+        for file in self.standalone_icons {
+            let key = match file.path() {
+                Some(path) => path
                     .file_stem()
                     .map(|s| s.to_string_lossy().to_string())
-                    .unwrap_or(String::new());
-                (key, file)
-            })
-            .collect();
+                    .unwrap_or(String::new()),
+                None => file.icon_name().to_owned(),
+            };
+
+            standalone_icons.entry(key).or_default().push(file);
+        }
+
+        let mut other_standalone_icons: HashMap<String, Vec<OtherIconFile>> = HashMap::new();
+        for file in self.other_standalone_icons {
+            other_standalone_icons
+                .entry(file.icon_name().to_owned())
+                .or_default()
+                .push(file);
+        }
 
         Icons {
             standalone_icons,
+            other_standalone_icons,
             themes,
+            profiler: None,
+            aliases: HashMap::new(),
+            fallback_icon: None,
+            file_provider: self.file_provider,
+            default_theme,
+            pointer_themes,
+            scan_counters: self.scan_counters,
+            inheritance_order: self.inheritance_order,
+            refresh: None,
+            #[cfg(feature = "embedded-fallback")]
+            embedded_fallback: false,
         }
     }
 
@@ -331,11 +843,29 @@ impl IconLocations {
     /// - For each theme, finding the appropriate theme index file (see [`ThemeInfo`] and [`ThemeIndex`](crate::theme::ThemeIndex))
     /// - Find all (transitive) dependencies of themes, performing the same operation(s) for them, and
     /// - Pruning duplicate references in the dependency graph: after `resolve`, each theme has a
-    ///   _direct acyclic graph_ of its dependents computed.
+    ///   _direct acyclic graph_ of its dependents computed. The order in which each theme's
+    ///   parents are searched is set with [`IconSearch::inheritance_order`].
+    ///
+    /// Themes that inherit from each other in a cycle have that cycle broken at the theme whose
+    /// internal name sorts first: it keeps the other themes of the cycle as parents, while they
+    /// lose it as a parent.
     pub fn resolve(&self) -> HashMap<OsString, Arc<Theme>> {
         self.resolve_only(self.themes_directories.keys())
     }
 
+    /// Like [resolve](Self::resolve), but calls `progress` after each icon theme candidate is
+    /// parsed, whether it turned out to be a valid theme or not.
+    ///
+    /// Parsing the themes is what makes resolving slow, so this can be used to show progress,
+    /// for example on a splash screen. Each candidate is parsed once, so the last call reports
+    /// [`processed`](Progress::processed) equal to [`total`](Progress::total).
+    pub fn resolve_with_progress(
+        &self,
+        mut progress: impl FnMut(Progress),
+    ) -> HashMap<OsString, Arc<Theme>> {
+        self.resolve_only_with_progress(self.themes_directories.keys(), &mut progress)
+    }
+
     /// Like [resolve](Self::resolve), but with a restricted set of themes to resolve.
     ///
     /// This still collects all dependencies of the icon themes: for example, \
@@ -346,6 +876,34 @@ impl IconLocations {
     /// Thus, a call to `resolve_only(&["Adwaita"])` will still return a map with `Adwaita`,
     ///   `AdwaitaLegacy` and `hicolor`.
     pub fn resolve_only<I, S>(&self, theme_names: I) -> HashMap<OsString, Arc<Theme>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.resolve_only_with_progress(theme_names, &mut |_| {})
+    }
+
+    /// Like [resolve](Self::resolve), but only resolves the theme candidates whose internal name
+    /// passes `predicate`, for example to skip themes known to be broken.
+    ///
+    /// Like [resolve_only](Self::resolve_only), the dependencies of the themes resolved are always
+    /// collected, even if they don't pass the predicate themselves.
+    pub fn resolve_filtered(
+        &self,
+        predicate: impl Fn(&OsStr) -> bool,
+    ) -> HashMap<OsString, Arc<Theme>> {
+        self.resolve_only(
+            self.themes_directories
+                .keys()
+                .filter(|name| predicate(name.as_os_str())),
+        )
+    }
+
+    fn resolve_only_with_progress<I, S>(
+        &self,
+        theme_names: I,
+        progress: &mut dyn FnMut(Progress),
+    ) -> HashMap<OsString, Arc<Theme>>
     where
         I: IntoIterator<Item = S>,
         S: AsRef<OsStr>,
@@ -364,6 +922,7 @@ impl IconLocations {
             name: &OsStr,
             locations: &IconLocations,
             themes: &mut HashMap<OsString, Option<ThemeInfo>>,
+            on_parsed: &mut dyn FnMut(),
         ) {
             // Skip if we already have this theme.
             if themes.contains_key(name) {
@@ -380,6 +939,13 @@ impl IconLocations {
                     None
                 }
             };
+
+            // names that aren't candidates (e.g. missing parents) are rejected without parsing
+            // anything, so they don't count towards progress
+            if locations.themes_directories.contains_key(name) {
+                on_parsed();
+            }
+
             let info = themes.entry(name.to_os_string()).insert_entry(info);
 
             let Some(info) = info.get() else {
@@ -390,22 +956,29 @@ impl IconLocations {
 
             // Collect all parents of this theme:
             for parent in parents {
-                collect_themes(parent.as_ref(), locations, themes);
+                collect_themes(parent.as_ref(), locations, themes, on_parsed);
             }
         }
 
         // Map from theme names to their info:
         let mut themes = HashMap::new();
 
+        let total = self.themes_directories.len();
+        let mut processed = 0;
+        let mut on_parsed = || {
+            processed += 1;
+            progress(Progress { processed, total });
+        };
+
         // collect all required themes:
         for theme_name in theme_names {
             let theme_name = theme_name.as_ref();
-            collect_themes(theme_name, self, &mut themes);
+            collect_themes(theme_name, self, &mut themes, &mut on_parsed);
         }
 
         // make 100% sure we have `hicolor`, for the half-impossible edge-case of only collecting
         // themes that does not have hicolor in their inheritance tree
-        collect_themes("hicolor".as_ref(), self, &mut themes);
+        collect_themes("hicolor".as_ref(), self, &mut themes, &mut on_parsed);
         // of course, the user might be cursed and not have `hicolor` installed at all!
         // that is troubling, but we'll see that it is handled correctly below.
 
@@ -414,10 +987,14 @@ impl IconLocations {
         // we'll also split them up, as `theme_chains` borrows names from `theme_names`,
         // but we need to mutate theme_info later (during the borrow) to avoid
         // cloning the info
-        let (theme_names, mut theme_info): (Vec<_>, Vec<_>) = themes
+        // the themes are sorted by name, so that themes are constructed in the same order every
+        // time. that order decides where inheritance cycles are broken, see `construct`.
+        let mut themes = themes
             .into_iter()
             .flat_map(|(key, value)| value.map(|v| (key, Some(v))))
-            .unzip();
+            .collect::<Vec<_>>();
+        themes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let (theme_names, mut theme_info): (Vec<_>, Vec<_>) = themes.into_iter().unzip();
 
         // the Options are there just so we can take info out of the vec without messing up the order.
         debug_assert!(theme_info.iter().all(Option::is_some));
@@ -426,88 +1003,93 @@ impl IconLocations {
         // if not, there's no use in inserting hicolor into the inheritance tree later
         let hicolor_idx = theme_names.iter().position(|name| name == "hicolor");
 
-        // Time to find the optimal ancestry for each theme.
-        // As hicolor _should_ have all icons by default, and all themes depend on hicolor at some depth,
-        // DFS would de facto end up in hicolor before ever trying the second theme in an Inherits set.
-        // Therefore, BFS is the only sensible option, but the spec doesn't define this.
+        // parents are looked up by name a lot below, so index the names once up-front
+        let theme_indices = theme_names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.as_os_str(), idx))
+            .collect::<HashMap<_, _>>();
+
+        // Time to find the optimal ancestry for each theme, in the configured order.
+        // See `InheritanceOrder` for why that is BFS by default, even though the spec doesn't
+        // define this.
 
         // indexed by the position in our theme_names/theme_info vecs
         let number_of_themes = theme_names.len();
-        let mut theme_chains = Vec::<Vec<usize>>::with_capacity(number_of_themes);
-
-        for theme_idx in 0..number_of_themes {
-            let mut chain = Vec::from([theme_idx]);
-
-            let mut cursor = 0;
-            while let Some(node_idx) = chain.get(cursor).copied() {
-                cursor += 1;
+        let parents_of = |idx: &usize| {
+            let Some(Some(info)) = theme_info.get(*idx) else {
+                return vec![];
+            };
 
-                let Some(Some(info)) = theme_info.get(node_idx) else {
-                    continue;
-                };
+            info.index
+                .inherits
+                .iter()
+                // parents that weren't found are invalid
+                .filter_map(|parent| theme_indices.get(OsStr::new(parent)).copied())
+                .collect()
+        };
+
+        let theme_chains = (0..number_of_themes)
+            .map(|theme_idx| {
+                inheritance_chain(theme_idx, parents_of, hicolor_idx, self.inheritance_order)
+            })
+            .collect::<Vec<_>>();
 
-                for parent in &info.index.inherits {
-                    let Some(parent_idx) = theme_names
-                        .iter()
-                        .position(|name| *name.as_os_str() == **parent)
-                    else {
-                        // this parent was invalid
-                        continue;
-                    };
+        // at this point `theme_chains` contains the lookup order of each theme's parents.
+        // the parents of a theme have to be constructed before the theme itself, which we do by
+        // constructing each theme's parents first, recursively.
 
-                    // add this parent, removing any previous occurrences
-                    chain.retain(|idx| *idx != parent_idx);
-                    chain.push(parent_idx);
-                }
-            }
+        // again indexed by theme indices, None values mean the theme hasn't been constructed yet.
+        // the goal is that, by the end of the for loop, that this only contains `Some`s.
+        let mut full_themes = vec![None::<Arc<Theme>>; number_of_themes];
 
-            // From the spec: "If no theme is specified, implementations are required to add the
-            //                 "hicolor" theme to the inheritance tree."
-            if let Some(hicolor_idx) = hicolor_idx {
-                chain.retain(|idx| *idx != hicolor_idx);
-                chain.push(hicolor_idx);
+        fn construct(
+            theme_idx: usize,
+            theme_chains: &[Vec<usize>],
+            theme_info: &mut [Option<ThemeInfo>],
+            full_themes: &mut [Option<Arc<Theme>>],
+            file_provider: &Arc<dyn FileProvider>,
+        ) -> Option<Arc<Theme>> {
+            if let Some(theme) = &full_themes[theme_idx] {
+                return Some(theme.clone());
             }
 
-            theme_chains.push(chain);
+            // the info is only missing for themes that are being constructed already, meaning
+            // themes inherit from each other in a cycle. the cycle is broken here.
+            let info = theme_info[theme_idx].take()?;
+
+            let parents = theme_chains[theme_idx]
+                .iter()
+                .skip(1) // the first in the chain is the theme itself, which we'll ignore—it's not a parent.
+                .filter_map(|&parent_idx| {
+                    construct(
+                        parent_idx,
+                        theme_chains,
+                        theme_info,
+                        full_themes,
+                        file_provider,
+                    )
+                })
+                .collect();
+
+            let theme = Arc::new(Theme::with_file_provider(
+                info,
+                parents,
+                file_provider.clone(),
+            ));
+            full_themes[theme_idx] = Some(theme.clone());
+
+            Some(theme)
         }
 
-        // at this point `theme_chains` contains a _topological order_ for each theme's parents,
-        // meaning we can easily iterate over it, constructing `Theme`s, assuming at every point
-        // that each parent already has a `Theme` created for it :)
-
-        // again indexed by theme indices, None values mean the theme hasn't been processed yet.
-        // the goal is that, by the end of the for loop, that this only contains `Some`s.
-        // we rely on the topological order of chains to always have all the prerequisite themes
-        // present already in this map!
-        let mut full_themes = vec![None::<Arc<Theme>>; number_of_themes];
-
-        for chain in &theme_chains {
-            // go from last theme to first, as all dependencies are "forward" in the chain:
-            for theme_idx in chain.iter().copied().rev() {
-                let theme_info = theme_info[theme_idx].take();
-
-                let Some(theme_info) = theme_info else {
-                    // the option was None, meaning this theme was processed already :-)
-                    continue;
-                };
-
-                let parents = &theme_chains[theme_idx];
-                let parents = parents
-                    .iter()
-                    .skip(1) // the first in the chain is the theme itself, which we'll ignore—it's not a parent.
-                    .copied()
-                    // unwrap OK because, by the topological order, all of these parents
-                    // should already be present in the array:
-                    .map(|parent_idx| Arc::clone(full_themes[parent_idx].as_ref().unwrap()))
-                    .collect();
-
-                let theme = Theme {
-                    info: theme_info,
-                    inherits_from: parents,
-                };
-
-                full_themes[theme_idx] = Some(Arc::new(theme));
-            }
+        for theme_idx in 0..number_of_themes {
+            construct(
+                theme_idx,
+                &theme_chains,
+                &mut theme_info,
+                &mut full_themes,
+                &self.file_provider,
+            );
         }
 
         debug_assert!(full_themes.iter().all(Option::is_some));
@@ -544,7 +1126,76 @@ impl IconLocations {
             .get(internal_name)
             .ok_or_else(|| std::io::Error::other(ThemeParseError::NotAnIconTheme))?;
 
-        ThemeInfo::new_from_folders(internal_name.to_owned(), theme.clone())
+        ThemeInfo::new_from_folders_counted(
+            internal_name.to_owned(),
+            theme.clone(),
+            &self.scan_counters,
+            self.file_provider.as_ref(),
+        )
+    }
+
+    /// Find the theme candidates that are "pointers" to another theme: their index has an
+    /// `Inherits` key, but no `Directories`. The `default` theme of many systems is one.
+    ///
+    /// Pointers aren't valid themes, so they're never among `themes`. Each is mapped to the first
+    /// theme it inherits from that is among `themes`.
+    fn pointer_themes(&self, themes: &HashMap<OsString, Arc<Theme>>) -> HashMap<String, String> {
+        self.themes_directories
+            .iter()
+            .filter(|(name, _)| !themes.contains_key(*name))
+            .filter_map(|(name, dirs)| {
+                let index_location =
+                    dirs.iter()
+                        .map(|dir| dir.join("index.theme"))
+                        .find(|path| {
+                            self.scan_counters.count_stat();
+                            self.file_provider.exists(path)
+                        })?;
+
+                self.scan_counters.count_file_read();
+                let index = self.file_provider.read(&index_location).ok()?;
+                let target = ThemeIndex::parse_pointer(&index)?
+                    .into_iter()
+                    .map(|name| name.trim().to_owned())
+                    .find(|name| themes.contains_key(OsStr::new(name)))?;
+
+                Some((name.to_str()?.to_owned(), target))
+            })
+            .collect()
+    }
+
+    /// Find the name of the theme the `default` theme points at, if there is one.
+    ///
+    /// On many systems, the default icon theme is configured by a `default` theme whose index only
+    /// contains an `Inherits` key naming the actual theme. This reads the first `index.theme` of
+    /// the `default` theme and returns the first theme it inherits from.
+    ///
+    /// The returned theme is not guaranteed to be installed.
+    pub fn default_theme(&self) -> Option<String> {
+        let dirs = self.themes_directories.get(OsStr::new("default"))?;
+
+        let index_location = dirs
+            .iter()
+            .map(|dir| dir.join("index.theme"))
+            .find(|path| {
+                self.scan_counters.count_stat();
+                self.file_provider.exists(path)
+            })?;
+
+        self.scan_counters.count_file_read();
+        let index = self.file_provider.read(&index_location).ok()?;
+
+        ThemeIndex::parse_inherits(&index)
+            .ok()?
+            .into_iter()
+            .map(|name| name.trim().to_owned())
+            .find(|name| !name.is_empty())
+    }
+
+    /// Returns the file system operations performed so far to find these locations, and to load
+    /// and resolve themes from them.
+    pub fn scan_stats(&self) -> ScanStats {
+        self.scan_counters.snapshot()
     }
 
     /// Look up a standalone icon by name.
@@ -560,13 +1211,17 @@ impl IconLocations {
     {
         let name = icon_name.as_ref();
 
-        self.standalone_icons
-            .iter()
-            .find(|icon| icon.path().file_stem() == Some(name))
+        self.standalone_icons.iter().find(|icon| match icon.path() {
+            Some(path) => path.file_stem() == Some(name),
+            None => icon.icon_name() == name,
+        })
     }
 }
 
 /// Anything that turns into an iterator of things that can become paths can be turned into an [`IconSearch`].
+///
+/// Note that a single [`Path`](std::path::Path) iterates over its components, so use
+/// [`IconSearch::new_single`] to search just one directory.
 impl<I, P> From<I> for IconSearch
 where
     I: IntoIterator<Item = P>,
@@ -579,8 +1234,27 @@ where
     }
 }
 
+/// The environment variable that replaces the default search directories, see [`IconSearch::new`].
+const SEARCH_PATH_VAR: &str = "ICON_SEARCH_PATH";
+
+/// Splits the value of [`SEARCH_PATH_VAR`] into directories, or returns `None` if it holds none.
+fn search_path_directories(search_path: &OsStr) -> Option<Vec<PathBuf>> {
+    let directories = std::env::split_paths(search_path)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect::<Vec<_>>();
+
+    (!directories.is_empty()).then_some(directories)
+}
+
 impl Default for IconSearch {
     fn default() -> Self {
+        if let Some(directories) = std::env::var_os(SEARCH_PATH_VAR)
+            .as_deref()
+            .and_then(search_path_directories)
+        {
+            return directories.into();
+        }
+
         // "By default, apps should look in $HOME/.icons (for backwards compatibility),
         // in $XDG_DATA_DIRS/icons
         // and in /usr/share/pixmaps (in that order)."
@@ -607,11 +1281,62 @@ impl Default for IconSearch {
 
 #[cfg(test)]
 pub(crate) mod test {
-    use crate::search::IconSearch;
-    use std::collections::HashSet;
-    use std::path::PathBuf;
+    use crate::search::{IconSearch, InheritanceOrder, Progress, search_path_directories};
+    use crate::{FileEntry, FileProvider};
+    use std::collections::{HashMap, HashSet};
+    use std::ffi::OsStr;
+    use std::ops::Deref;
+    use std::path::{Path, PathBuf};
+
+    pub static PROJ_ROOT: &'static str = env!("CARGO_MANIFEST_DIR");
+
+    /// A directory for tests to write files into, which is removed when dropped, even if the
+    /// test panics.
+    pub struct TestDir(PathBuf);
+
+    impl Deref for TestDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    impl AsRef<Path> for TestDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
 
-    static PROJ_ROOT: &'static str = env!("CARGO_MANIFEST_DIR");
+    impl From<&TestDir> for PathBuf {
+        fn from(dir: &TestDir) -> Self {
+            dir.0.clone()
+        }
+    }
+
+    /// Creates an empty directory for tests to write files into.
+    pub fn test_dir(name: &str) -> TestDir {
+        let dir = std::env::temp_dir().join(format!("icon-test-{}-{name}", std::process::id()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        TestDir(dir)
+    }
+
+    /// Writes a theme called `name` with the given index into `base_dir`.
+    pub fn write_theme(base_dir: &Path, name: &str, index: &str) {
+        let theme_dir = base_dir.join(name);
+
+        std::fs::create_dir_all(&theme_dir).unwrap();
+        std::fs::write(theme_dir.join("index.theme"), index).unwrap();
+    }
 
     pub fn test_search() -> IconSearch {
         IconSearch::new_empty().add_directories([
@@ -697,4 +1422,462 @@ pub(crate) mod test {
         let other_theme = locations.load_single_theme("OtherTheme").unwrap();
         assert_eq!(other_theme.internal_name, "OtherTheme");
     }
+
+    #[test]
+    fn test_scan_stats() {
+        let icons = test_search().search().icons();
+        let stats = icons.scan_stats();
+
+        assert_eq!(stats.read_dir_calls, 2, "one for each search directory");
+        assert_eq!(stats.file_reads, 2, "one for each theme");
+        // one for each search directory, and each theme's index is found in the first directory
+        // checked. Then, to detect changes later, the modification times of the search
+        // directories, of TestTheme's two directories and index, and of OtherTheme's directory
+        // and index.
+        assert_eq!(stats.stat_calls, 2 + 2 + 2 + 3 + 2);
+    }
+
+    #[test]
+    fn test_default_theme() {
+        let base_dir = test_dir("default-theme");
+        write_theme(
+            &base_dir,
+            "default",
+            "[Icon Theme]\nInherits=TestTheme,hicolor\n",
+        );
+
+        let icons = test_search().add_directories([&base_dir]).search().icons();
+        assert_eq!(icons.resolve_default_theme().as_deref(), Some("TestTheme"));
+
+        // without a `default` theme, there's nothing to resolve
+        let icons = test_search().search().icons();
+        assert_eq!(icons.resolve_default_theme(), None);
+    }
+
+    #[test]
+    fn test_pointer_themes() {
+        let base_dir = test_dir("pointer-themes");
+        write_theme(
+            &base_dir,
+            "default",
+            "[Icon Theme]\nInherits=Missing,TestTheme\n",
+        );
+        // points at nothing that exists
+        write_theme(
+            &base_dir,
+            "cursors",
+            "[Icon Theme]\nName=Cursors\nInherits=Missing\n",
+        );
+
+        let icons = test_search().add_directories([&base_dir]).search().icons();
+        assert!(!icons.themes.contains_key(OsStr::new("default")));
+        let theme = icons.theme("default").unwrap();
+        assert_eq!(theme.info.internal_name, "TestTheme");
+        assert!(icons.theme("cursors").is_none());
+
+        assert_eq!(
+            icons.find_icon("happy", 16, 1, "default"),
+            icons.find_icon("happy", 16, 1, "TestTheme")
+        );
+        // the parents of the theme pointed at are searched too
+        assert!(icons.find_icon("pixel", 1, 1, "default").is_some());
+
+        #[cfg(feature = "cache")]
+        {
+            let mut icons_cache = crate::IconsCache::from_icons(icons);
+            assert!(icons_cache.find_icon("pixel", 1, 1, "default").is_some());
+            assert_eq!(
+                icons_cache.find_icons(&["pixel", "happy"], 1, 1, "default"),
+                icons_cache.find_icons(&["pixel", "happy"], 1, 1, "TestTheme")
+            );
+            assert!(icons_cache.find_icons(&["pixel"], 1, 1, "default")[0].is_some());
+        }
+    }
+
+    #[test]
+    fn test_resolve_with_progress() {
+        let locations = test_search().search().into_icon_locations();
+
+        let mut reports = Vec::new();
+        let themes = locations.resolve_with_progress(|progress| reports.push(progress));
+
+        assert_eq!(themes.len(), 2);
+        assert_eq!(
+            reports,
+            [
+                Progress {
+                    processed: 1,
+                    total: 2
+                },
+                Progress {
+                    processed: 2,
+                    total: 2
+                },
+            ]
+        );
+    }
+
+    /// A file system that only exists in memory.
+    struct MemoryFileProvider(HashMap<PathBuf, Vec<u8>>);
+
+    impl FileProvider for MemoryFileProvider {
+        fn exists(&self, path: &Path) -> bool {
+            self.0.keys().any(|file| file.starts_with(path))
+        }
+
+        fn read_dir(&self, path: &Path) -> std::io::Result<Vec<FileEntry>> {
+            let entries = self
+                .0
+                .keys()
+                .filter_map(|file| {
+                    let mut components = file.strip_prefix(path).ok()?.components();
+                    let name = components.next()?;
+
+                    Some(FileEntry {
+                        path: path.join(name),
+                        is_dir: components.next().is_some(),
+                    })
+                })
+                .collect::<HashSet<_>>();
+
+            Ok(entries.into_iter().collect())
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            self.0
+                .keys()
+                .any(|file| file.starts_with(path) && file != path)
+        }
+
+        fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+            self.0
+                .get(path)
+                .cloned()
+                .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+        }
+    }
+
+    #[test]
+    fn test_file_provider() {
+        let files = [
+            (
+                "/virtual/Memory/index.theme",
+                "[Icon Theme]\nName=Memory\nDirectories=16x16\n\n[16x16]\nSize=16\n",
+            ),
+            ("/virtual/Memory/16x16/foo.png", ""),
+            ("/virtual/bar.svg", ""),
+        ]
+        .map(|(path, contents)| (PathBuf::from(path), contents.as_bytes().to_vec()));
+
+        let icons = IconSearch::new_from(vec!["/virtual".into()])
+            .with_file_provider(MemoryFileProvider(HashMap::from(files)))
+            .search()
+            .icons();
+
+        let icon = icons.find_icon("foo", 16, 1, "Memory").unwrap();
+        assert_eq!(
+            icon.path().unwrap(),
+            Path::new("/virtual/Memory/16x16/foo.png")
+        );
+        let icon = icons.find_standalone_icon("bar").unwrap();
+        assert_eq!(icon.path().unwrap(), Path::new("/virtual/bar.svg"));
+
+        assert_eq!(icons.find_all_icons().count(), 1);
+        assert!(icons.find_icon("foo", 16, 1, "hicolor").is_none());
+    }
+
+    #[test]
+    fn test_from_single_directory() {
+        let dir = Path::new(PROJ_ROOT).join("resources/test_icons");
+
+        let search = IconSearch::new_single(dir.as_path());
+        assert_eq!(search.dirs, vec![dir.clone()]);
+
+        let search = IconSearch::new_single(dir.clone());
+        assert_eq!(search.dirs, vec![dir.clone()]);
+
+        let search = IconSearch::from([&dir, &dir]);
+        assert_eq!(search.dirs, [dir.clone(), dir.clone()]);
+
+        // any iterator of paths
+        let search = IconSearch::from(
+            ["resources/test_icons"]
+                .iter()
+                .map(|dir| Path::new(PROJ_ROOT).join(dir)),
+        );
+        assert_eq!(search.dirs, [dir]);
+    }
+
+    #[test]
+    fn test_merge_locations() {
+        let mut locations = IconSearch::new_empty()
+            .add_directories([PathBuf::from(PROJ_ROOT).join("resources/test_icons")])
+            .search()
+            .into_icon_locations();
+        let alt_locations = IconSearch::new_empty()
+            .add_directories([PathBuf::from(PROJ_ROOT).join("resources/test_icons_alt")])
+            .search()
+            .into_icon_locations();
+
+        assert_eq!(locations.themes_directories.len(), 1);
+
+        locations.merge(alt_locations);
+
+        // OtherTheme is new, TestTheme gains the directory from test_icons_alt
+        assert_eq!(locations.themes_directories.len(), 2);
+        assert_eq!(
+            locations.themes_directories[OsStr::new("TestTheme")].len(),
+            2
+        );
+
+        let merged = locations.resolve();
+        let direct = test_search().search().into_icon_locations().resolve();
+        assert_eq!(merged.len(), direct.len());
+        assert_eq!(
+            merged[OsStr::new("TestTheme")].info,
+            direct[OsStr::new("TestTheme")].info
+        );
+    }
+
+    #[test]
+    fn test_dedup_theme_directories() {
+        let search = || {
+            test_search().add_directories([PathBuf::from(PROJ_ROOT).join("resources/test_icons/")])
+        };
+
+        let locations = search().search().into_icon_locations();
+        assert_eq!(
+            locations.themes_directories[OsStr::new("TestTheme")].len(),
+            3
+        );
+
+        let locations = search()
+            .dedup_theme_directories(true)
+            .search()
+            .into_icon_locations();
+        assert_eq!(
+            locations.themes_directories[OsStr::new("TestTheme")].len(),
+            2
+        );
+
+        // directories are resolved through the file provider
+        let files = HashMap::from([(
+            PathBuf::from("/virtual/Memory/index.theme"),
+            b"[Icon Theme]\nName=Memory\nDirectories=\n".to_vec(),
+        )]);
+        let locations = IconSearch::new_from(vec!["/virtual".into(), "/virtual/".into()])
+            .with_file_provider(MemoryFileProvider(files))
+            .dedup_theme_directories(true)
+            .search()
+            .into_icon_locations();
+        assert_eq!(
+            locations.themes_directories[OsStr::new("Memory")],
+            [Path::new("/virtual/Memory")]
+        );
+    }
+
+    #[test]
+    fn test_resolve_many_themes() {
+        const THEMES: usize = 300;
+
+        let dir = test_dir("many-themes");
+        for i in 0..THEMES {
+            let parents = [i + 1, i + 2]
+                .into_iter()
+                .filter(|parent| *parent < THEMES)
+                .map(|parent| format!("theme{parent}"))
+                .collect::<Vec<_>>()
+                .join(",");
+
+            write_theme(
+                &dir,
+                &format!("theme{i}"),
+                &format!("[Icon Theme]\nName=Theme {i}\nInherits={parents}\nDirectories=\n"),
+            );
+        }
+
+        let themes = IconSearch::new_from(vec![dir.to_path_buf()])
+            .search()
+            .into_icon_locations()
+            .resolve();
+        assert_eq!(themes.len(), THEMES);
+
+        // the chain of the first theme is every other theme, in order
+        let chain = themes[OsStr::new("theme0")]
+            .inherits_from
+            .iter()
+            .map(|theme| theme.info.internal_name.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let expected = (1..THEMES).map(|i| format!("theme{i}")).collect::<Vec<_>>();
+        assert_eq!(chain, expected);
+    }
+
+    #[test]
+    fn test_file_as_search_directory() {
+        let dir = test_dir("file-as-search-directory");
+        let file = dir.join("icons");
+        std::fs::write(&file, "").unwrap();
+
+        let icons = IconSearch::new_from(vec![file, dir.join("missing")])
+            .search()
+            .icons();
+        assert!(icons.is_empty());
+        assert_eq!(icons.scan_stats().read_dir_calls, 0);
+    }
+
+    #[test]
+    fn test_inheritance_order() {
+        let dir = test_dir("inheritance-order");
+        for (name, inherits) in [
+            ("Root", "A,B"),
+            ("A", "C"),
+            ("B", "A"),
+            ("C", ""),
+            ("hicolor", ""),
+        ] {
+            write_theme(
+                &dir,
+                name,
+                &format!("[Icon Theme]\nName={name}\nInherits={inherits}\nDirectories=\n"),
+            );
+        }
+
+        let chain = |order| {
+            let icons = IconSearch::new_from(vec![dir.to_path_buf()])
+                .inheritance_order(order)
+                .search()
+                .icons();
+            icons.theme_chain("Root").unwrap()
+        };
+
+        // B inherits A, so A is searched after B
+        assert_eq!(
+            chain(InheritanceOrder::Bfs),
+            ["Root", "B", "A", "C", "hicolor"]
+        );
+        assert_eq!(
+            chain(InheritanceOrder::Dfs),
+            ["Root", "A", "C", "B", "hicolor"]
+        );
+    }
+
+    #[test]
+    fn test_inheritance_cycle() {
+        let dir = test_dir("inheritance-cycle");
+        for (name, inherits) in [("A", "B"), ("B", "A"), ("C", "A")] {
+            write_theme(
+                &dir,
+                name,
+                &format!("[Icon Theme]\nName={name}\nInherits={inherits}\nDirectories=\n"),
+            );
+        }
+
+        // the themes are resolved in a new order every time, which must not matter
+        for _ in 0..10 {
+            let icons = IconSearch::new_from(vec![dir.to_path_buf()])
+                .search()
+                .icons();
+
+            assert_eq!(icons.theme_chain("A").unwrap(), ["A", "B"]);
+            assert_eq!(icons.theme_chain("B").unwrap(), ["B"]);
+            assert_eq!(icons.theme_chain("C").unwrap(), ["C", "B", "A"]);
+        }
+    }
+
+    #[test]
+    fn test_resolve_filtered() {
+        let locations = test_search().search().into_icon_locations();
+
+        let themes = locations.resolve_filtered(|name| name != "OtherTheme");
+        assert!(themes.contains_key(OsStr::new("TestTheme")));
+        // still collected, as TestTheme inherits it
+        assert!(themes.contains_key(OsStr::new("OtherTheme")));
+
+        let themes = locations.resolve_filtered(|name| name != "TestTheme");
+        assert!(!themes.contains_key(OsStr::new("TestTheme")));
+        assert!(themes.contains_key(OsStr::new("OtherTheme")));
+    }
+
+    #[test]
+    fn test_exclude_themes() {
+        let icons = test_search()
+            .exclude_themes(&["TestTheme"])
+            .search()
+            .icons();
+        assert!(icons.theme("TestTheme").is_none());
+        assert!(icons.theme("OtherTheme").is_some());
+
+        // excluded parents are skipped, like missing ones
+        let icons = test_search()
+            .exclude_themes(&["OtherTheme"])
+            .search()
+            .icons();
+        assert!(icons.theme("OtherTheme").is_none());
+        assert_eq!(
+            icons.theme_chain("TestTheme").unwrap(),
+            ["TestTheme".to_owned()]
+        );
+        assert!(icons.find_icon("happy", 16, 1, "TestTheme").is_some());
+        assert!(icons.find_icon("pixel", 1, 1, "TestTheme").is_none());
+    }
+
+    #[test]
+    fn test_standalone_extensions() {
+        let dir = test_dir("standalone-extensions");
+        for file in [
+            "legacy.gif",
+            "legacy.ico",
+            "animated.GIF",
+            "modern.png",
+            "photo.bmp",
+        ] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let icons = IconSearch::new_from(vec![dir.to_path_buf()])
+            .search()
+            .icons();
+        assert_eq!(
+            icons.standalone_icon_names().collect::<Vec<_>>(),
+            ["modern"]
+        );
+        assert!(icons.find_other_standalone_icon("legacy").is_none());
+
+        let icons = IconSearch::new_from(vec![dir.to_path_buf()])
+            .standalone_extensions(&["ico", ".gif"])
+            .search()
+            .icons();
+        // other formats are kept apart from the formats of the Icon Theme specification
+        assert_eq!(
+            icons.standalone_icon_names().collect::<Vec<_>>(),
+            ["modern"]
+        );
+        assert!(icons.find_standalone_icon("legacy").is_none());
+        assert!(icons.find_other_standalone_icon("photo").is_none());
+
+        // extensions are preferred in the configured order
+        let icon = icons.find_other_standalone_icon("legacy").unwrap();
+        assert_eq!(icon.extension(), "ico");
+        assert_eq!(icon.path(), dir.join("legacy.ico"));
+
+        let icon = icons.find_other_standalone_icon("animated").unwrap();
+        assert_eq!(icon.extension(), "gif");
+        assert_eq!(icon.icon_name(), "animated");
+    }
+
+    #[test]
+    fn test_search_path_directories() {
+        assert_eq!(
+            search_path_directories(OsStr::new("/a/icons:/b/icons")),
+            Some(vec![PathBuf::from("/a/icons"), PathBuf::from("/b/icons")])
+        );
+        // empty entries are skipped
+        assert_eq!(
+            search_path_directories(OsStr::new(":/a/icons::")),
+            Some(vec![PathBuf::from("/a/icons")])
+        );
+        // no directories at all means the defaults are used
+        assert_eq!(search_path_directories(OsStr::new("")), None);
+        assert_eq!(search_path_directories(OsStr::new("::")), None);
+    }
 }
@@ -1,11 +1,18 @@
 use crate::ThemeParseError::MissingRequiredAttribute;
-use crate::icon::IconFile;
+use crate::icon::{FileType, IconFile};
+use crate::profile::{LookupOutcome, LookupProfile, ScanCounters};
+use crate::provider::{FileProvider, StdFileProvider};
 use freedesktop_entry_parser::low_level::{SectionBytes, SectionBytesIter};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsString;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Instant;
 
-pub(crate) type DirectoryRef = usize;
+/// Refers to one of a theme's directories, by its position in the theme's
+/// [directory list](ThemeIndex::directories). Resolve it with [`Theme::directory`].
+pub type DirectoryRef = usize;
 
 /// An icon theme.
 pub struct Theme {
@@ -16,9 +23,146 @@ pub struct Theme {
     /// When querying for an icon that doesn't exist in this theme, the themes in its `inherits_from`
     /// list will be checked for that icon instead.
     pub inherits_from: Vec<Arc<Theme>>,
+    /// Used to check which icon files exist.
+    pub(crate) file_provider: Arc<dyn FileProvider>,
+    /// The order to check directories in, per requested size and scale. See [`DirectoryOrder`].
+    directory_orders: RwLock<HashMap<(u32, u32), Arc<DirectoryOrder>>>,
+}
+
+/// The order in which a theme's directories are checked for an icon of some size and scale.
+///
+/// Computing this requires going over and sorting all of a theme's directories, so it is done once
+/// per size and scale, and then reused for all lookups at that size and scale.
+struct DirectoryOrder {
+    /// The number of directories at the start of `directories` that match the size exactly.
+    exact: usize,
+    /// All directories of the theme: those that match exactly first, in the order of the index,
+    /// then all others from the closest to the furthest in size.
+    directories: Vec<DirectoryRef>,
 }
 
 impl Theme {
+    /// Create a theme from its info and the (already resolved) themes it inherits from.
+    ///
+    /// Icons are looked up on the file system through [`StdFileProvider`]. Usually, themes are
+    /// created through an [`IconSearch`](crate::IconSearch) instead, which also resolves their
+    /// parents.
+    pub fn new(info: ThemeInfo, inherits_from: Vec<Arc<Theme>>) -> Theme {
+        Self::with_file_provider(info, inherits_from, Arc::new(StdFileProvider))
+    }
+
+    pub(crate) fn with_file_provider(
+        info: ThemeInfo,
+        inherits_from: Vec<Arc<Theme>>,
+        file_provider: Arc<dyn FileProvider>,
+    ) -> Theme {
+        Theme {
+            info,
+            inherits_from,
+            file_provider,
+            directory_orders: RwLock::default(),
+        }
+    }
+
+    /// Load a single theme from the directory it lives in, without searching for other themes.
+    ///
+    /// The name of the directory is used as the theme's internal name.
+    ///
+    /// <div class="warning">
+    ///
+    /// The returned theme has no resolved parents: its `inherits_from` list is empty, even if the
+    /// theme's index declares themes it inherits from. Lookups will only find icons in this theme.
+    /// To get a theme with working inheritance, use [`IconSearch`](crate::IconSearch) instead.
+    ///
+    /// </div>
+    pub fn load_from_dir(path: &Path) -> std::io::Result<Theme> {
+        let internal_name = match path.file_name() {
+            Some(name) => name.to_os_string(),
+            // paths like `.` or `..` don't have a file name of their own
+            None => path
+                .canonicalize()?
+                .file_name()
+                .ok_or_else(|| std::io::Error::other(ThemeParseError::NotAnIconTheme))?
+                .to_os_string(),
+        };
+
+        let info = ThemeInfo::new_from_folders(internal_name, vec![path.to_owned()])?;
+
+        Ok(Theme::new(info, vec![]))
+    }
+
+    /// Reclassifies directories whose index doesn't declare a type, but which only contain SVG
+    /// icons, as [DirectoryType::Scalable].
+    ///
+    /// Directories without a `Type` default to [DirectoryType::Threshold], meaning their icons are
+    /// only used close to the directory's size, even when those icons are vector graphics. This
+    /// heuristic rescues such under-specified themes, by looking at the files in each directory
+    /// without a `Type`; directories that declare one, even `Type=Threshold`, are left alone. If a
+    /// reclassified directory doesn't declare `MinSize` or `MaxSize`, its icons are assumed to be
+    /// usable from 1 pixel or up to 512 pixels (or the directory's size, if larger) respectively,
+    /// like the scalable directories of the `hicolor` theme.
+    ///
+    /// <div class="warning">
+    ///
+    /// This deviates from the theme's index, and from the Icon Theme specification, so it is
+    /// never done automatically. It lists every directory of the theme without a `Type`, which may
+    /// be slow for large themes.
+    ///
+    /// </div>
+    pub fn infer_directory_types(&mut self) {
+        let ThemeInfo {
+            base_dirs, index, ..
+        } = &mut self.info;
+
+        for directory in &mut index.directories {
+            // only directories that fell back to the default type are reclassified
+            if directory.declared_type.is_some() {
+                continue;
+            }
+
+            let mut file_types = base_dirs
+                .iter()
+                .flat_map(|base_dir| {
+                    self.file_provider
+                        .read_dir(&base_dir.join(&directory.directory_name))
+                })
+                .flatten()
+                .filter(|entry| !entry.is_dir)
+                .filter_map(|entry| FileType::from_path_ext(&entry.path))
+                .peekable();
+
+            // empty directories say nothing about their type
+            if file_types.peek().is_none() || !file_types.all(|file_type| file_type.is_scalable()) {
+                continue;
+            }
+
+            directory.directory_type = DirectoryType::Scalable;
+            if directory.declared_min_size.is_none() {
+                directory.min_size = 1;
+            }
+            if directory.declared_max_size.is_none() {
+                directory.max_size = directory.size.max(512);
+            }
+        }
+
+        // the order of directories depends on their types
+        self.directory_orders
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Checks whether this theme (transitively) inherits from the theme with the given internal
+    /// name, meaning lookups in this theme may fall back to icons of that theme.
+    ///
+    /// A theme does not inherit from itself.
+    pub fn inherits_transitively(&self, internal_name: &str) -> bool {
+        // `inherits_from` already contains all transitive parents
+        self.inherits_from
+            .iter()
+            .any(|parent| parent.info.internal_name == internal_name)
+    }
+
     /// Find an icon in this theme or any of its dependencies, with scale equal to 1.
     ///
     /// Also see [find_icon](Theme::find_icon)
@@ -36,57 +180,585 @@ impl Theme {
     /// - `size`: the size, in pixels, desired. The returned icon may not be this exact size in case an exact match couldn't be found.
     /// - `scale`: the scale at which the icon will be displayed.
     pub fn find_icon(&self, icon_name: &str, size: u32, scale: u32) -> Option<IconFile> {
-        self.find_icon_here(icon_name, size, scale).or_else(|| {
-            // or find it in one of our parents
-            self.inherits_from
-                .iter()
-                .find_map(|theme| theme.find_icon_here(icon_name, size, scale))
+        self.find_icon_profiled(icon_name, size, scale, &mut LookupProfile::default())
+    }
+
+    pub(crate) fn find_icon_profiled(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        profile: &mut LookupProfile,
+    ) -> Option<IconFile> {
+        self.find_match(icon_name, size, scale, &[], profile)
+            .map(|(_, _, icon)| icon)
+    }
+
+    /// Like [find_icon](Theme::find_icon), but only returns icons that
+    /// [match the size and scale](DirectoryIndex::matches_icon_size) exactly, never the closest
+    /// match. Raster icons in [Scalable](DirectoryType::Scalable) directories only match the
+    /// directory's nominal size.
+    ///
+    /// This is useful for renderers that can't scale icons at all. As with
+    /// [find_icon](Theme::find_icon), the theme's parents are searched if it has no exact match.
+    pub fn find_icon_exact(&self, icon_name: &str, size: u32, scale: u32) -> Option<IconFile> {
+        std::iter::once(self)
+            .chain(self.inherits_from.iter().map(Arc::as_ref))
+            .find_map(|theme| {
+                theme.exact_sub_dirs_for(size, scale).find_map(|sub_dir| {
+                    let file_names = Self::exact_file_names_for(icon_name, sub_dir, size, scale);
+                    theme.find_icon_in_directory_profiled(
+                        &theme.info.base_dirs,
+                        &file_names,
+                        sub_dir,
+                        &mut LookupProfile::default(),
+                    )
+                })
+            })
+    }
+
+    /// Like [find_icon](Theme::find_icon), but returns every format of the icon present in the
+    /// directory `find_icon` picks, for example both the PNG and the SVG, so the caller can choose
+    /// one at render time.
+    ///
+    /// Only the single best matching directory is considered: formats in other directories, even
+    /// ones of the same size, are not returned. The icons are ordered like [`FileType::types`].
+    /// If the theme is split over multiple base directories, the first base directory holding a
+    /// format wins. Returns an empty list if the icon isn't found.
+    pub fn find_icon_formats(&self, icon_name: &str, size: u32, scale: u32) -> Vec<IconFile> {
+        let Some((theme, directory, _)) =
+            self.find_match(icon_name, size, scale, &[], &mut LookupProfile::default())
+        else {
+            return vec![];
+        };
+
+        FileType::types()
+            .into_iter()
+            .filter_map(|file_type| {
+                let file_name = [format!("{icon_name}.{}", file_type.ext())];
+                theme.find_icon_in_directory_profiled(
+                    &theme.info.base_dirs,
+                    &file_name,
+                    directory,
+                    &mut LookupProfile::default(),
+                )
+            })
+            .collect()
+    }
+
+    /// Like [find_icon](Theme::find_icon), but if the icon isn't found, retries with its symbolic
+    /// variant toggled: `foo-symbolic` falls back to `foo`, and `foo` to `foo-symbolic`.
+    ///
+    /// Toolkits often ask for the symbolic variant of an icon, while many themes only ship the
+    /// full-color one, or the other way around. The retry searches this theme and its parents
+    /// again, so an exact name anywhere in the chain is preferred over a toggled one.
+    pub fn find_icon_sym_aware(&self, icon_name: &str, size: u32, scale: u32) -> Option<IconFile> {
+        self.find_icon(icon_name, size, scale).or_else(|| {
+            let toggled = match icon_name.strip_suffix("-symbolic") {
+                // neither an empty name nor `-symbolic` have a variant
+                _ if icon_name.is_empty() => return None,
+                Some("") => return None,
+                Some(full_color) => full_color.to_owned(),
+                None => format!("{icon_name}-symbolic"),
+            };
+
+            self.find_icon(&toggled, size, scale)
         })
     }
 
+    /// Like [find_icon](Theme::find_icon), but also returns the theme and directory the icon was
+    /// found in.
+    ///
+    /// Only files of the types in `accept` are considered, see [find_icon_typed](Theme::find_icon_typed).
+    pub(crate) fn find_match(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        accept: &[FileType],
+        profile: &mut LookupProfile,
+    ) -> Option<(&Theme, &DirectoryIndex, IconFile)> {
+        self.find_match_until(icon_name, size, scale, accept, None, profile)
+    }
+
+    /// Like [find_match](Theme::find_match), but gives up once `deadline` has passed.
+    /// The deadline is checked before each directory is probed.
+    pub(crate) fn find_match_until(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        accept: &[FileType],
+        deadline: Option<Instant>,
+        profile: &mut LookupProfile,
+    ) -> Option<(&Theme, &DirectoryIndex, IconFile)> {
+        std::iter::once(self)
+            // or find it in one of our parents
+            .chain(self.inherits_from.iter().map(Arc::as_ref))
+            .find_map(|theme| {
+                let (dir, icon) = theme
+                    .find_match_here_until(icon_name, size, scale, accept, deadline, profile)?;
+                Some((theme, dir, icon))
+            })
+    }
+
+    /// Like [find_icon](Theme::find_icon), but only considers icon files of the given types.
+    ///
+    /// Within a directory, files are checked in the order the types are given in, so earlier types
+    /// are preferred over later ones. Directory matching is not affected: an icon of an accepted
+    /// type in a directory of a better size is still preferred over one of an earlier type.
+    /// An empty `accept` list accepts all types, in the default order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use icon::{FileType, Icons};
+    ///
+    /// let icons = Icons::new();
+    /// if let Some(theme) = icons.theme("hicolor") {
+    ///     // prefer SVGs, but fall back to PNGs
+    ///     theme.find_icon_typed("firefox", 48, 1, &[FileType::Svg, FileType::Png]);
+    /// }
+    /// ```
+    pub fn find_icon_typed(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        accept: &[FileType],
+    ) -> Option<IconFile> {
+        self.find_match(
+            icon_name,
+            size,
+            scale,
+            accept,
+            &mut LookupProfile::default(),
+        )
+        .map(|(_, _, icon)| icon)
+    }
+
+    /// Lazily walks the themes [find_icon](Theme::find_icon) searches, in order: this theme, then
+    /// its parents. For each theme, yields its internal name and the icon found in that theme
+    /// alone, if any.
+    ///
+    /// Unlike `find_icon`, which stops at the first theme that has the icon, this exposes the
+    /// whole walk, which is useful to debug why a certain icon is picked. The first `Some` yielded
+    /// is the icon `find_icon` would return. Themes are only searched as the iterator is advanced.
+    pub fn find_icon_trace<'a>(
+        &'a self,
+        icon_name: &'a str,
+        size: u32,
+        scale: u32,
+    ) -> impl Iterator<Item = (String, Option<IconFile>)> + 'a {
+        std::iter::once(self)
+            .chain(self.inherits_from.iter().map(Arc::as_ref))
+            .map(move |theme| {
+                let name = theme.info.internal_name.to_string_lossy().into_owned();
+                (name, theme.find_icon_here(icon_name, size, scale))
+            })
+    }
+
     /// Find an icon in this theme only.
     ///
     /// Do not use this function if you need normal icon finding behaviour: use [find_icon](Theme::find_icon) instead.
+    ///
+    /// # Base directory precedence
+    ///
+    /// A theme may be split over multiple [base directories](ThemeInfo::base_dirs), which are
+    /// ordered like the directories of the [`IconSearch`](crate::IconSearch) they were found in.
+    /// Directories are checked in order of how well they match the size and scale, and within a
+    /// directory, the first base directory that holds the icon wins, whatever its file type. So an
+    /// icon in a better matching directory of a later base directory shadows one in an earlier
+    /// base directory. To prefer earlier base directories instead, use
+    /// [find_icon_here_base_dir_first](Theme::find_icon_here_base_dir_first).
     pub fn find_icon_here(&self, icon_name: &str, size: u32, scale: u32) -> Option<IconFile> {
-        // first, try to find an exact icon size match:
-        let exact_sub_dirs = self.exact_sub_dirs_for(size, scale);
-        if let Some(exact_match_icon) = exact_sub_dirs
-            .flat_map(|exact_sub_dir| self.find_icon_in_directory(icon_name, exact_sub_dir))
-            .next()
-        {
-            // and return it if found!
-            return Some(exact_match_icon);
+        self.find_match_here(icon_name, size, scale, &[], &mut LookupProfile::default())
+            .map(|(_, icon)| icon)
+    }
+
+    /// Like [find_icon](Theme::find_icon), but among exact matches, prefers the icon of the first
+    /// base directory, in the order of the search directories. See
+    /// [find_icon_here_base_dir_first](Theme::find_icon_here_base_dir_first).
+    pub fn find_icon_base_dir_first(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+    ) -> Option<IconFile> {
+        self.find_match_base_dir_first(icon_name, size, scale, &mut LookupProfile::default())
+            .map(|(_, _, icon)| icon)
+    }
+
+    /// Like [find_icon_base_dir_first](Theme::find_icon_base_dir_first), but also returns the
+    /// theme and directory the icon was found in.
+    pub(crate) fn find_match_base_dir_first(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        profile: &mut LookupProfile,
+    ) -> Option<(&Theme, &DirectoryIndex, IconFile)> {
+        std::iter::once(self)
+            .chain(self.inherits_from.iter().map(Arc::as_ref))
+            .find_map(|theme| {
+                let (dir, icon) =
+                    theme.find_match_here_base_dir_first(icon_name, size, scale, profile)?;
+                Some((theme, dir, icon))
+            })
+    }
+
+    /// Like [find_icon_here](Theme::find_icon_here), but if multiple directories match the size
+    /// and scale exactly, the icon of the first [base directory](ThemeInfo::base_dirs) that has one
+    /// is returned, rather than that of the first matching directory.
+    ///
+    /// This is useful for themes split over multiple base directories in an order that matters,
+    /// like an override directory in front of a system directory. If there is no exact match,
+    /// this is the same as [find_icon_here](Theme::find_icon_here). Exact matches are determined
+    /// like in [find_icon_exact](Theme::find_icon_exact).
+    pub fn find_icon_here_base_dir_first(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+    ) -> Option<IconFile> {
+        self.find_match_here_base_dir_first(icon_name, size, scale, &mut LookupProfile::default())
+            .map(|(_, icon)| icon)
+    }
+
+    /// Like [find_icon_here_base_dir_first](Theme::find_icon_here_base_dir_first), but also
+    /// returns the directory the icon was found in.
+    fn find_match_here_base_dir_first(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        profile: &mut LookupProfile,
+    ) -> Option<(&DirectoryIndex, IconFile)> {
+        for base_dir in &self.info.base_dirs {
+            for sub_dir in self.exact_sub_dirs_for(size, scale) {
+                let file_names = Self::exact_file_names_for(icon_name, sub_dir, size, scale);
+                let base_dir = std::slice::from_ref(base_dir);
+
+                if let Some(icon) =
+                    self.find_icon_in_directory_profiled(base_dir, &file_names, sub_dir, profile)
+                {
+                    profile.themes_checked += 1;
+                    profile.outcome = LookupOutcome::Exact;
+                    return Some((sub_dir, icon));
+                }
+            }
         }
 
-        // no exact match: try to find a match as close as possible instead.
+        self.find_match_here(icon_name, size, scale, &[], profile)
+    }
+
+    /// Like [find_icon_here](Theme::find_icon_here), but also returns the directory the icon was
+    /// found in.
+    pub(crate) fn find_match_here(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        accept: &[FileType],
+        profile: &mut LookupProfile,
+    ) -> Option<(&DirectoryIndex, IconFile)> {
+        self.find_match_here_until(icon_name, size, scale, accept, None, profile)
+    }
+
+    /// Like [find_match_here](Theme::find_match_here), but gives up once `deadline` has passed.
+    /// The deadline is checked before each directory is probed.
+    pub(crate) fn find_match_here_until(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+        accept: &[FileType],
+        deadline: Option<Instant>,
+        profile: &mut LookupProfile,
+    ) -> Option<(&DirectoryIndex, IconFile)> {
+        profile.themes_checked += 1;
+
+        let file_names = Self::file_names_for(icon_name, accept);
+        let order = self.directory_order(size, scale);
 
-        // in order to reduce file exist syscalls,
-        // we opt to do the hopefully _less expensive_ operation of sorting the subdirectories instead,
-        // from the smallest size_distance to largest.
+        // directories matching the size exactly come first, followed by the closest matches.
         // that gives us the assurance that the first icon found, is the best one.
-        let mut sub_dirs = self.info.index.directories.iter().collect::<Vec<_>>();
-        sub_dirs.sort_by_key(|sub_dir| sub_dir.size_distance(size, scale));
-
-        for sub_dir in sub_dirs {
-            for base_dir in &self.info.base_dirs {
-                for file_name in &Self::possible_file_names_for(icon_name) {
-                    let path = base_dir
-                        .join(sub_dir.directory_name.as_str())
-                        .join(file_name);
-                    if path.exists()
-                        && let Some(file) = IconFile::from_path(&path)
-                    {
-                        return Some(file);
-                    }
-                }
+        for (position, &sub_dir) in order.directories.iter().enumerate() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return None;
+            }
+
+            let sub_dir = &self.info.index.directories[sub_dir];
+
+            if let Some(icon) = self.find_icon_in_directory_profiled(
+                &self.info.base_dirs,
+                &file_names,
+                sub_dir,
+                profile,
+            ) {
+                profile.outcome = if position < order.exact {
+                    LookupOutcome::Exact
+                } else {
+                    LookupOutcome::Closest
+                };
+                return Some((sub_dir, icon));
             }
         }
 
         None
     }
 
-    #[allow(unused)] // Used with certain crate features.
-    pub(crate) fn find_icon_files(
+    /// Returns the order to check this theme's directories in for an icon of the given size and
+    /// scale, computing it if this is the first lookup at that size and scale.
+    fn directory_order(&self, size: u32, scale: u32) -> Arc<DirectoryOrder> {
+        let cached = self
+            .directory_orders
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(size, scale))
+            .cloned();
+
+        if let Some(order) = cached {
+            return order;
+        }
+
+        let directories = &self.info.index.directories;
+
+        // first, the directories that may hold an exact icon size match:
+        let mut order = (0..directories.len())
+            .filter(|&dir| directories[dir].matches_size(size, scale))
+            .collect::<Vec<_>>();
+        let exact = order.len();
+
+        // then, to find a match as close as possible, all other directories.
+        // in order to reduce file exist syscalls, these are sorted from the smallest size_distance
+        // to largest, preferring directories of the requested scale.
+        let mut closest = (0..directories.len())
+            .filter(|&dir| !directories[dir].matches_size(size, scale))
+            .collect::<Vec<_>>();
+        closest.sort_by_key(|&dir| directories[dir].lookup_order(size, scale));
+        order.append(&mut closest);
+
+        let order = Arc::new(DirectoryOrder {
+            exact,
+            directories: order,
+        });
+
+        self.directory_orders
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry((size, scale))
+            .or_insert(order)
+            .clone()
+    }
+
+    /// Find a scalable (vector) version of an icon in this theme or any of its dependencies,
+    /// regardless of size.
+    ///
+    /// Only [FileType::Svg] icons in [DirectoryType::Scalable] directories are considered.
+    /// Compressed `.svgz` files are not looked up, as the Icon Theme specification doesn't list
+    /// them as an icon file type.
+    /// Returns `None` if the icon has no scalable version, even if raster versions exist.
+    pub fn find_scalable_icon(&self, icon_name: &str) -> Option<IconFile> {
+        let file_name = &format!("{icon_name}.{}", FileType::Svg.ext());
+
+        std::iter::once(self)
+            .chain(self.inherits_from.iter().map(Arc::as_ref))
+            .flat_map(|theme| {
+                let scalable_dirs = theme
+                    .info
+                    .index
+                    .directories
+                    .iter()
+                    .filter(|dir| dir.directory_type == DirectoryType::Scalable);
+
+                scalable_dirs.flat_map(move |dir| {
+                    theme.info.base_dirs.iter().map(move |base_dir| {
+                        (theme, base_dir.join(&dir.directory_name).join(file_name))
+                    })
+                })
+            })
+            // each theme's directories are accessed through its own file provider
+            .find(|(theme, path)| theme.file_provider.exists(path))
+            .and_then(|(theme, path)| {
+                let data = theme.file_provider.embedded_contents(&path);
+                IconFile::from_path_buf(path).map(|file| file.with_embedded_data(data))
+            })
+    }
+
+    /// Compute the range of (unscaled) sizes an icon is available at, in this theme or any of its
+    /// dependencies.
+    ///
+    /// Only directories that actually contain the icon are considered. [Scalable](DirectoryType::Scalable)
+    /// directories contribute their `min_size` and `max_size`, all others their nominal `size`.
+    /// Returns `None` if the icon can't be found at all.
+    pub fn available_size_range(&self, icon_name: &str) -> Option<RangeInclusive<u32>> {
+        std::iter::once(self)
+            .chain(self.inherits_from.iter().map(Arc::as_ref))
+            .flat_map(|theme| {
+                theme.find_icon_files(icon_name).map(|(dir, _)| {
+                    let dir = &theme.info.index.directories[dir];
+                    match dir.directory_type {
+                        DirectoryType::Scalable => (dir.min_size, dir.max_size),
+                        DirectoryType::Fixed | DirectoryType::Threshold => (dir.size, dir.size),
+                    }
+                })
+            })
+            .reduce(|(min, max), (lower, upper)| (min.min(lower), max.max(upper)))
+            .map(|(min, max)| min..=max)
+    }
+
+    /// Find an icon that represents this theme, for example to show a preview in a theme chooser.
+    ///
+    /// This is the theme's [example](ThemeIndex#structfield.example) icon if it has one.
+    /// If it doesn't, or the icon can't be found, a few common icons are tried instead.
+    /// As with [find_icon](Theme::find_icon), icons are also looked up in the theme's parents.
+    pub fn preview_icon(&self, size: u32, scale: u32) -> Option<IconFile> {
+        const FALLBACK_ICONS: [&str; 4] =
+            ["folder", "user-home", "image-x-generic", "text-x-generic"];
+
+        self.info
+            .index
+            .example
+            .as_deref()
+            .into_iter()
+            .chain(FALLBACK_ICONS)
+            .find_map(|icon_name| self.find_icon(icon_name, size, scale))
+    }
+
+    /// Like [find_icon](Theme::find_icon), but cross-checks the file type of each candidate icon
+    /// against the type of the directory it was found in.
+    ///
+    /// Some themes place raster images (PNG, XPM) in directories declared `Type=Scalable`.
+    /// Normally, such icons would be considered usable at any size within the directory's
+    /// `min_size` and `max_size`. This function instead treats them as if they lived in a `Fixed`
+    /// directory of the directory's nominal size. See [`DirectoryIndex::icon_size_distance`].
+    ///
+    /// This is more expensive than [find_icon](Theme::find_icon) when no exact match exists, as
+    /// every directory of a theme needs to be checked for the icon.
+    pub fn find_icon_checked(&self, icon_name: &str, size: u32, scale: u32) -> Option<IconFile> {
+        self.find_icon_here_checked(icon_name, size, scale)
+            .or_else(|| {
+                self.inherits_from
+                    .iter()
+                    .find_map(|theme| theme.find_icon_here_checked(icon_name, size, scale))
+            })
+    }
+
+    /// Like [find_icon_here](Theme::find_icon_here), but cross-checks file types against directory
+    /// types. See [find_icon_checked](Theme::find_icon_checked).
+    pub fn find_icon_here_checked(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+    ) -> Option<IconFile> {
+        // this is the same two-phase lookup as `find_icon_here`, except that we only know the
+        // distance of an icon after having found its file.
+        let exact_match = self
+            .exact_sub_dirs_for(size, scale)
+            .flat_map(|sub_dir| {
+                self.find_icon_in_directory(icon_name, sub_dir)
+                    .filter(|icon| sub_dir.matches_icon_size(icon.file_type(), size, scale))
+            })
+            .next();
+
+        if exact_match.is_some() {
+            return exact_match;
+        }
+
+        self.find_icon_files(icon_name)
+            .min_by_key(|(dir, icon)| {
+                let dir = &self.info.index.directories[*dir];
+
+                (
+                    dir.icon_size_distance(icon.file_type(), size, scale),
+                    dir.scale != scale,
+                )
+            })
+            .map(|(_, icon)| icon)
+    }
+
+    /// Like [find_icon](Theme::find_icon), but avoids icons that would have to be upscaled, as
+    /// upscaling blurs icons.
+    ///
+    /// If no icon matches the size and scale exactly, the icon of the smallest directory that is
+    /// at least as large as the requested size is returned, to be downscaled. Only if there is
+    /// none, the icon of the largest smaller directory is returned. Sizes are compared in pixels,
+    /// i.e. multiplied by the scale.
+    ///
+    /// Like [find_icon_checked](Theme::find_icon_checked), this is more expensive than
+    /// [find_icon](Theme::find_icon) when no exact match exists.
+    pub fn find_icon_no_upscale(&self, icon_name: &str, size: u32, scale: u32) -> Option<IconFile> {
+        self.find_icon_here_no_upscale(icon_name, size, scale)
+            .or_else(|| {
+                self.inherits_from
+                    .iter()
+                    .find_map(|theme| theme.find_icon_here_no_upscale(icon_name, size, scale))
+            })
+    }
+
+    /// Like [find_icon_here](Theme::find_icon_here), but avoids upscaling icons. See
+    /// [find_icon_no_upscale](Theme::find_icon_no_upscale).
+    pub fn find_icon_here_no_upscale(
+        &self,
+        icon_name: &str,
+        size: u32,
+        scale: u32,
+    ) -> Option<IconFile> {
+        let exact_match = self
+            .exact_sub_dirs_for(size, scale)
+            .find_map(|sub_dir| self.find_icon_in_directory(icon_name, sub_dir));
+
+        if exact_match.is_some() {
+            return exact_match;
+        }
+
+        self.find_icon_files(icon_name)
+            .min_by_key(|(dir, _)| {
+                let dir = &self.info.index.directories[*dir];
+                let upscaled = dir.largest_size() * dir.scale < size * scale;
+
+                (upscaled, dir.size_distance(size, scale), dir.scale != scale)
+            })
+            .map(|(_, icon)| icon)
+    }
+
+    /// Checks whether a directory of this theme exists on disk, in any of the theme's base
+    /// directories.
+    ///
+    /// Directories listed in a theme's index are not guaranteed to actually exist. Lookups treat
+    /// missing directories as empty.
+    pub fn directory_exists(&self, directory: &DirectoryIndex) -> bool {
+        self.info.base_dirs.iter().any(|base_dir| {
+            let path = base_dir.join(&directory.directory_name);
+            self.file_provider.is_dir(&path)
+        })
+    }
+
+    /// Returns the paths of all of this theme's directories that exist, as seen by the theme's
+    /// [`FileProvider`]. See [`ThemeInfo::existing_directories`].
+    pub fn existing_directories(&self) -> Vec<PathBuf> {
+        self.info
+            .existing_directories_with(self.file_provider.as_ref())
+    }
+
+    /// Returns the directory of this theme that `directory` refers to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `directory` doesn't refer to one of this theme's directories, i.e. if it was
+    /// obtained from another theme.
+    pub fn directory(&self, directory: DirectoryRef) -> &DirectoryIndex {
+        &self.info.index.directories[directory]
+    }
+
+    /// Find the files of an icon in every directory of this theme (but not its parents), along with
+    /// the directory each was found in. Use [`directory`](Theme::directory) to resolve the latter.
+    ///
+    /// At most one file is returned per directory, preferring file types in the order of
+    /// [`FileType::types`]. The files are returned in the order of the theme's directories.
+    pub fn find_icon_files(
         &self,
         icon_name: &str,
     ) -> impl Iterator<Item = (DirectoryRef, IconFile)> {
@@ -101,6 +773,53 @@ impl Theme {
             })
     }
 
+    /// Find all icons of this theme (but not its parents), grouped by the nominal size of the
+    /// directory they're in, for example to browse a theme by size.
+    ///
+    /// Icons of [Scalable](DirectoryType::Scalable) directories are grouped under the directory's
+    /// nominal `size` too. Directories of different scales but the same size share a group.
+    /// Within a group, icons are sorted by name.
+    pub fn icons_by_size(&self) -> BTreeMap<u32, Vec<IconFile>> {
+        let mut icons_by_size = BTreeMap::<u32, Vec<IconFile>>::new();
+
+        for directory in &self.info.index.directories {
+            icons_by_size
+                .entry(directory.size)
+                .or_default()
+                .extend(self.icons_in_directory(directory));
+        }
+
+        icons_by_size.retain(|_, icons| !icons.is_empty());
+        for icons in icons_by_size.values_mut() {
+            icons.sort_by(|a, b| (a.icon_name(), a.path()).cmp(&(b.icon_name(), b.path())));
+        }
+
+        icons_by_size
+    }
+
+    /// Lists the icons in a single directory of this theme, in all of the theme's
+    /// [base directories](ThemeInfo::base_dirs), for example to load a theme browser one
+    /// directory at a time. Use [`directory`](Theme::directory) to get a directory by its
+    /// [`DirectoryRef`].
+    ///
+    /// The icons are listed lazily, one base directory at a time, in the order the base
+    /// directories are in. Within a base directory, the order is that of the file system.
+    /// Base directories that don't have the directory, or can't be read, are skipped. An icon
+    /// present in multiple base directories is listed once for each.
+    pub fn icons_in_directory(&self, directory: &DirectoryIndex) -> impl Iterator<Item = IconFile> {
+        self.info
+            .base_dirs
+            .iter()
+            .map(|base_dir| base_dir.join(&directory.directory_name))
+            .flat_map(|dir| self.file_provider.read_dir(&dir)) // skip directories we can't read
+            .flatten()
+            .filter(|entry| !entry.is_dir)
+            .flat_map(|entry| {
+                let data = self.file_provider.embedded_contents(&entry.path);
+                IconFile::from_path_buf(entry.path).map(|icon| icon.with_embedded_data(data))
+            })
+    }
+
     fn exact_sub_dirs_for(
         &self,
         size: u32,
@@ -113,10 +832,36 @@ impl Theme {
             .filter(move |sub_dir| sub_dir.matches_size(size, scale))
     }
 
-    fn possible_file_names_for(icon_name: &str) -> [String; 3] {
-        const EXTENSIONS: [&str; 3] = ["png", "xpm", "svg"];
+    /// The file names to look for in `directory`, of the file types whose icons match the size and
+    /// scale exactly there. See [`DirectoryIndex::matches_icon_size`].
+    fn exact_file_names_for(
+        icon_name: &str,
+        directory: &DirectoryIndex,
+        size: u32,
+        scale: u32,
+    ) -> Vec<String> {
+        FileType::types()
+            .into_iter()
+            .filter(|file_type| directory.matches_icon_size(*file_type, size, scale))
+            .map(|file_type| format!("{icon_name}.{}", file_type.ext()))
+            .collect()
+    }
+
+    pub(crate) fn possible_file_names_for(icon_name: &str) -> [String; 3] {
+        FileType::types().map(|file_type| format!("{icon_name}.{}", file_type.ext()))
+    }
+
+    /// The file names to look for, for the given file types in order of preference. An empty
+    /// list of file types means all of them, in the default order.
+    fn file_names_for(icon_name: &str, accept: &[FileType]) -> Vec<String> {
+        if accept.is_empty() {
+            return Self::possible_file_names_for(icon_name).into();
+        }
 
-        EXTENSIONS.map(|ext| format!("{icon_name}.{ext}"))
+        accept
+            .iter()
+            .map(|file_type| format!("{icon_name}.{}", file_type.ext()))
+            .collect()
     }
 
     pub(crate) fn find_icon_in_directory(
@@ -126,17 +871,39 @@ impl Theme {
     ) -> Option<IconFile> {
         let file_names = Self::possible_file_names_for(icon_name);
 
-        for base_dir in &self.info.base_dirs {
-            for file_name in &file_names {
+        self.find_icon_in_directory_profiled(
+            &self.info.base_dirs,
+            &file_names,
+            directory,
+            &mut LookupProfile::default(),
+        )
+    }
+
+    /// Probes `directory` in each of `base_dirs` in order, for each of `file_names` in order,
+    /// returning the first icon found.
+    fn find_icon_in_directory_profiled(
+        &self,
+        base_dirs: &[PathBuf],
+        file_names: &[String],
+        directory: &DirectoryIndex,
+        profile: &mut LookupProfile,
+    ) -> Option<IconFile> {
+        profile.directories_checked += 1;
+
+        for base_dir in base_dirs {
+            for file_name in file_names {
                 let path = base_dir
                     .join(directory.directory_name.as_str())
                     .join(file_name);
 
-                let path_exists = path.exists();
+                profile.files_checked += 1;
+                let path_exists = self.file_provider.exists(&path);
 
                 if path_exists && let Some(file) = IconFile::from_path(&path) {
                     // exact match!
-                    return Some(file);
+                    return Some(
+                        file.with_embedded_data(self.file_provider.embedded_contents(&path)),
+                    );
                 }
             }
         }
@@ -148,7 +915,7 @@ impl Theme {
 /// Information about an icon theme.
 ///
 /// Its formal description (called the index) can be found in the `index` field.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ThemeInfo {
     /// The name of the directory wherein this theme lives.
     ///
@@ -174,6 +941,7 @@ pub struct ThemeInfo {
 ///
 /// This type is returned by [ThemeIndex::parse] and indirectly by [ThemeInfo::new_from_folders].
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum ThemeParseError {
     /// Missing the "Icon Theme" section.
     #[error("missing Icon Theme index or section")]
@@ -181,6 +949,9 @@ pub enum ThemeParseError {
     /// An attribute that is required, is missing.
     #[error("missing attribute `{0}`")]
     MissingRequiredAttribute(&'static str),
+    /// The theme's `Name` is empty, or consists only of whitespace.
+    #[error("the theme name is empty")]
+    EmptyName,
     /// The file isn't encoded in UTF-8.
     #[error("the input wasn't in utf-8")]
     NotUtf8(#[from] std::str::Utf8Error),
@@ -206,17 +977,46 @@ impl ThemeInfo {
     /// lives.
     ///
     /// This function will parse the first `index.theme` file found in the directories passed in.
+    /// An `index.theme` that is a dangling symlink doesn't count as found: the next directory's
+    /// `index.theme` is used instead.
     pub fn new_from_folders(
         internal_name: OsString,
         folders: Vec<PathBuf>,
     ) -> std::io::Result<Self> {
-        let index_location = folders
-            .iter()
-            .map(|f| f.join("index.theme"))
-            .find(|index_path| index_path.exists())
-            .ok_or_else(|| std::io::Error::other(ThemeParseError::NotAnIconTheme))?;
-
-        let index = ThemeIndex::parse_from_file(index_location.as_path())?;
+        Self::new_from_folders_counted(
+            internal_name,
+            folders,
+            &ScanCounters::default(),
+            &StdFileProvider,
+        )
+    }
+
+    pub(crate) fn new_from_folders_counted(
+        internal_name: OsString,
+        folders: Vec<PathBuf>,
+        scan_counters: &ScanCounters,
+        file_provider: &dyn FileProvider,
+    ) -> std::io::Result<Self> {
+        let index_location = folders
+            .iter()
+            .map(|f| f.join("index.theme"))
+            .find(|index_path| {
+                scan_counters.count_stat();
+                // dangling symlinks don't exist, so they are skipped as if there was no index
+                let exists = file_provider.exists(index_path);
+
+                #[cfg(feature = "log")]
+                if !exists && file_provider.is_symlink(index_path) {
+                    log::warn!("skipping dangling symlink {index_path:?} to a theme index");
+                }
+
+                exists
+            })
+            .ok_or_else(|| std::io::Error::other(ThemeParseError::NotAnIconTheme))?;
+
+        scan_counters.count_file_read();
+        let bytes = file_provider.read(&index_location)?;
+        let index = ThemeIndex::parse(&bytes).map_err(std::io::Error::other)?;
 
         Ok(Self {
             internal_name,
@@ -225,6 +1025,56 @@ impl ThemeInfo {
             index,
         })
     }
+
+    /// Returns the paths of all of this theme's directories that exist on disk.
+    ///
+    /// A directory of the index may exist in several base directories, so may be listed multiple
+    /// times. Paths are ordered by the directory's position in the index, then by base directory.
+    ///
+    /// The file system is accessed through [`StdFileProvider`]. To use the [`FileProvider`] of a
+    /// theme instead, use [`Theme::existing_directories`].
+    pub fn existing_directories(&self) -> Vec<PathBuf> {
+        self.existing_directories_with(&StdFileProvider)
+    }
+
+    pub(crate) fn existing_directories_with(
+        &self,
+        file_provider: &dyn FileProvider,
+    ) -> Vec<PathBuf> {
+        self.index
+            .directories
+            .iter()
+            .flat_map(|dir| {
+                self.base_dirs
+                    .iter()
+                    .map(|base_dir| base_dir.join(&dir.directory_name))
+            })
+            .filter(|path| file_provider.is_dir(path))
+            .collect()
+    }
+
+    /// Create a new `ThemeInfo` from the text of an index, paired with the folders at which the
+    /// theme's icons live.
+    ///
+    /// This is useful for themes whose index isn't on disk, for example because it is embedded
+    /// into the application with `include_str!`, while its icons are.
+    ///
+    /// As there is no index file, the `index_location` of the returned `ThemeInfo` is empty.
+    /// Use [`Theme::new`] to look up icons in the theme.
+    pub fn from_index_str(
+        internal_name: OsString,
+        index_text: &str,
+        base_dirs: Vec<PathBuf>,
+    ) -> Result<Self, ThemeParseError> {
+        let index = ThemeIndex::parse(index_text.as_bytes())?;
+
+        Ok(Self {
+            internal_name,
+            base_dirs,
+            index_location: PathBuf::new(),
+            index,
+        })
+    }
 }
 
 /// The "formal description" of a theme as specified by the Icon Theme specification.
@@ -235,7 +1085,7 @@ impl ThemeInfo {
 /// properties.
 ///
 /// All doc comments in *italics* below are copy-pasted from the XDG Icon Theme Specification.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ThemeIndex {
     /// *Short name of the icon theme, used in e.g. lists when selecting themes.*
     pub name: String,
@@ -272,6 +1122,38 @@ impl ThemeIndex {
         Ok(index)
     }
 
+    /// Parse only the `Inherits` list of an index.
+    ///
+    /// Unlike [`parse`](ThemeIndex::parse), this does not require any other attributes to be
+    /// present, so it also works for indices that merely point at other themes.
+    pub(crate) fn parse_inherits(bytes: &[u8]) -> Result<Vec<String>, ThemeParseError> {
+        let mut entry = freedesktop_entry_parser::low_level::parse_entry(bytes);
+
+        let icon_theme_section = entry.next().ok_or(ThemeParseError::NotAnIconTheme)??;
+
+        Ok(find_inherits(&icon_theme_section)?)
+    }
+
+    /// Parse the `Inherits` list of a "pointer" index: one that has no `Directories`, and only
+    /// points at other themes, like the `default` theme of many systems.
+    ///
+    /// Returns `None` if the index isn't a pointer, i.e. if it has `Directories` or doesn't
+    /// inherit from any theme.
+    pub(crate) fn parse_pointer(bytes: &[u8]) -> Option<Vec<String>> {
+        let mut entry = freedesktop_entry_parser::low_level::parse_entry(bytes);
+
+        let icon_theme_section = entry.next()?.ok()?;
+        if find_attr(&icon_theme_section, "Directories")
+            .ok()?
+            .is_some()
+        {
+            return None;
+        }
+
+        let inherits = find_inherits(&icon_theme_section).ok()?;
+        (!inherits.is_empty()).then_some(inherits)
+    }
+
     /// Parse an icon theme index directory from the content, in bytes, of the file.
     ///
     /// See [ThemeParseError] for the errors this function may return.
@@ -281,70 +1163,146 @@ impl ThemeIndex {
         let icon_theme_section: SectionBytes =
             entry.next().ok_or(ThemeParseError::NotAnIconTheme)??;
         let name: &str = find_attr_req(&icon_theme_section, "Name")?;
+        if name.trim().is_empty() {
+            return Err(ThemeParseError::EmptyName);
+        }
 
         // SPEC: `Comment` is required, but most icon theme developers can't be arsed to
         // include it! To make `icon` practical, we choose a default of an empty string instead.
         // `let comment = find_attr_req(&icon_theme_section, "Comment")?;`
         let comment = find_attr(&icon_theme_section, "Comment")?.unwrap_or("");
         // If no theme is specified, implementations are required to add the "hicolor" theme to the inheritance tree.
-        let inherits = find_attr(&icon_theme_section, "Inherits")?
-            .iter()
-            .flat_map(|s| s.split(',')) // `inherits` is a comma-separated string list
-            .map(Into::into)
-            .collect::<Vec<_>>();
+        let inherits = find_inherits(&icon_theme_section)?;
         let directories = find_attr_req(&icon_theme_section, "Directories")?
             .split(',')
-            .collect::<Vec<_>>();
+            .map(str::trim)
+            .collect::<HashSet<_>>();
         let scaled_directories = find_attr(&icon_theme_section, "ScaledDirectories")?
-            .map(|s| s.split(',').collect::<Vec<_>>());
+            .map(|s| s.split(',').map(str::trim).collect::<HashSet<_>>());
         let hidden = find_attr(&icon_theme_section, "Hidden")?
             .map(|s| s.parse())
             .transpose()?
             .unwrap_or(false);
         let example = find_attr(&icon_theme_section, "Example")?;
 
-        // all other sections should describe a directory in the directory list
-        let directories = entry
-            .filter_map(Result::ok)
-            .filter_map(|section| {
-                let title = str::from_utf8(section.title).ok()?;
+        // all other sections should describe a directory in the directory list.
+        // Some themes (e.g. KDE's) list directories in both `Directories` and `ScaledDirectories`,
+        // list them twice, or repeat a directory's section. Every directory is only described once:
+        // like with other key files, a later section replaces an earlier one of the same name.
+        let mut positions = HashMap::new();
+        let mut directory_indices = Vec::new();
 
-                let is_scaled_dir = scaled_directories
-                    .as_ref()
-                    .map(|d| d.contains(&title))
-                    .unwrap_or(false);
+        for section in entry.filter_map(Result::ok) {
+            let Ok(title) = str::from_utf8(section.title) else {
+                continue;
+            };
 
-                if !directories.contains(&title) && !is_scaled_dir {
-                    // this section isn't a listed directory! ignore!
-                    return None;
-                }
+            let is_scaled_dir = scaled_directories
+                .as_ref()
+                .map(|d| d.contains(title))
+                .unwrap_or(false);
 
-                let mut index = DirectoryIndex::parse(section);
+            if !directories.contains(title) && !is_scaled_dir {
+                // this section isn't a listed directory! ignore!
+                continue;
+            }
 
-                if is_scaled_dir && let Ok(index) = &mut index {
-                    index.is_scaled_dir = true;
-                }
+            let mut index = DirectoryIndex::parse(section)?;
+            index.is_scaled_dir |= is_scaled_dir;
 
-                Some(index)
-            })
-            .collect::<Result<Vec<_>, ThemeParseError>>()?;
+            match positions.get(title) {
+                Some(&position) => directory_indices[position] = index,
+                None => {
+                    positions.insert(title, directory_indices.len());
+                    directory_indices.push(index);
+                }
+            }
+        }
 
         Ok(Self {
             name: name.into(),
             comment: comment.into(),
             inherits,
-            directories,
+            directories: directory_indices,
             hidden,
             example: example.map(Into::into),
         })
     }
+
+    /// Serialize this index back into the format of an `index.theme` file, as bytes.
+    ///
+    /// See the [`Display`](std::fmt::Display) implementation, which produces the same text.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+}
+
+/// Writes the index in the format of an `index.theme` file: an `[Icon Theme]` section, followed by
+/// a section for every directory.
+///
+/// [Parsing](ThemeIndex::parse) the output results in the same `ThemeIndex`. Only the keys this
+/// crate models are written, so for example localized names of the original file are lost.
+/// Values are written as-is, and shouldn't contain line breaks.
+impl std::fmt::Display for ThemeIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |scaled: bool| {
+            self.directories
+                .iter()
+                .filter(|dir| dir.is_scaled_dir == scaled)
+                .map(|dir| dir.directory_name.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        writeln!(f, "[Icon Theme]")?;
+        writeln!(f, "Name={}", self.name)?;
+        if !self.comment.is_empty() {
+            writeln!(f, "Comment={}", self.comment)?;
+        }
+        if !self.inherits.is_empty() {
+            writeln!(f, "Inherits={}", self.inherits.join(","))?;
+        }
+        writeln!(f, "Directories={}", list(false))?;
+        if self.directories.iter().any(|dir| dir.is_scaled_dir) {
+            writeln!(f, "ScaledDirectories={}", list(true))?;
+        }
+        if self.hidden {
+            writeln!(f, "Hidden=true")?;
+        }
+        if let Some(example) = &self.example {
+            writeln!(f, "Example={example}")?;
+        }
+
+        for dir in &self.directories {
+            writeln!(f)?;
+            writeln!(f, "[{}]", dir.directory_name)?;
+            writeln!(f, "Size={}", dir.size)?;
+            writeln!(f, "Scale={}", dir.scale)?;
+            if let Some(context) = &dir.context {
+                writeln!(f, "Context={context}")?;
+            }
+            if dir.declared_type.is_some() || dir.directory_type != DirectoryType::Threshold {
+                writeln!(f, "Type={}", dir.directory_type.as_str())?;
+            }
+            // leaving out undeclared bounds keeps them from bounding `Threshold` directories
+            if dir.declared_max_size.is_some() || dir.max_size != dir.size {
+                writeln!(f, "MaxSize={}", dir.max_size)?;
+            }
+            if dir.declared_min_size.is_some() || dir.min_size != dir.size {
+                writeln!(f, "MinSize={}", dir.min_size)?;
+            }
+            writeln!(f, "Threshold={}", dir.threshold)?;
+        }
+
+        Ok(())
+    }
 }
 
 /// The "formal description" of a subdirectory in an Icon Theme, as specified by the Icon Theme
 /// specification.
 ///
 /// All doc comments in *italics* below are copy-pasted from the XDG Icon Theme Specification.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DirectoryIndex {
     /// The name of the subdirectory as found in the theme's index file.
     ///
@@ -368,6 +1326,14 @@ pub struct DirectoryIndex {
     pub min_size: u32,
     /// *The icons in this directory can be used if the size differ at most this much from the desired (unscaled) size. Defaults to *2* if not present.*
     pub threshold: u32,
+    /// The `MaxSize` given by the index (as corrected in [max_size](DirectoryIndex#structfield.max_size)),
+    /// or `None` if it was left out.
+    pub(crate) declared_max_size: Option<u32>,
+    /// The `MinSize` given by the index (as corrected in [min_size](DirectoryIndex#structfield.min_size)),
+    /// or `None` if it was left out.
+    pub(crate) declared_min_size: Option<u32>,
+    /// The `Type` given by the index, or `None` if it was left out.
+    pub(crate) declared_type: Option<DirectoryType>,
     // pub additional_values: HashMap<String, String>,
 }
 
@@ -383,19 +1349,32 @@ impl DirectoryIndex {
         // Valid types are Fixed, Scalable and Threshold.
         // The type decides what other keys in the section are used.
         // If not specified, the default is Threshold.
-        let directory_type = find_attr(&section, "Type")?
+        let declared_type: Option<DirectoryType> = find_attr(&section, "Type")?
             .map(|s| s.try_into())
             .transpose()
-            .map_err(|_| ThemeParseError::InvalidDirectoryType)?
-            .unwrap_or(DirectoryType::Threshold);
-        let max_size = find_attr(&section, "MaxSize")?
+            .map_err(|_| ThemeParseError::InvalidDirectoryType)?;
+        let directory_type = declared_type.unwrap_or(DirectoryType::Threshold);
+        let declared_max_size: Option<u32> = find_attr(&section, "MaxSize")?
             .map(|s| s.parse())
-            .transpose()?
-            .unwrap_or(size);
-        let min_size = find_attr(&section, "MinSize")?
+            .transpose()?;
+        let declared_min_size: Option<u32> = find_attr(&section, "MinSize")?
             .map(|s| s.parse())
-            .transpose()?
-            .unwrap_or(size);
+            .transpose()?;
+        let max_size = declared_max_size.unwrap_or(size);
+        let min_size = declared_min_size.unwrap_or(size);
+        // A range that doesn't include `size` can never match that size, and an inverted one
+        // never matches at all: correct these theme authoring mistakes instead of failing.
+        let size_range = clamp_size_range(min_size, max_size, size);
+        #[cfg(feature = "log")]
+        if size_range != (min_size, max_size) {
+            log::warn!(
+                "directory {dir_name:?} has the invalid size range {min_size}..={max_size} for \
+                 size {size}, using {}..={} instead",
+                size_range.0,
+                size_range.1
+            );
+        }
+        let (min_size, max_size) = size_range;
         let threshold = find_attr(&section, "Threshold")?
             .map(|s| s.parse())
             .transpose()?
@@ -411,10 +1390,30 @@ impl DirectoryIndex {
             max_size,
             min_size,
             threshold,
+            declared_max_size: declared_max_size.map(|_| max_size),
+            declared_min_size: declared_min_size.map(|_| min_size),
+            declared_type,
         })
     }
 
-    pub(crate) fn size_distance(&self, icon_size: u32, icon_scale: u32) -> u32 {
+    /// Computes how far off the icons in this directory are from the provided `icon_size` (in
+    /// pixels) and scale, in scaled pixels.
+    ///
+    /// A distance of 0 means the icons in this directory may be used as-is. Also see
+    /// [`matches_size`](DirectoryIndex::matches_size).
+    ///
+    /// As the distance is expressed in scaled pixels, a directory of a different scale than
+    /// requested can have the same distance as one of the requested scale: for example, 16x16
+    /// icons at scale 2 and 32x32 icons at scale 1 are both 32 pixels wide. Lookups break such
+    /// ties in favour of the directory with the requested scale.
+    ///
+    /// Directories with a `size` of 0 are invalid, and are considered maximally distant
+    /// (`u32::MAX`) from any size: their icons are only used if no other directory has the icon.
+    pub fn size_distance(&self, icon_size: u32, icon_scale: u32) -> u32 {
+        if self.size == 0 {
+            return u32::MAX;
+        }
+
         let size = icon_size * icon_scale;
 
         match self.directory_type {
@@ -430,8 +1429,8 @@ impl DirectoryIndex {
                 }
             }
             DirectoryType::Threshold => {
-                let lower = (self.size - self.threshold) * self.scale;
-                let higher = (self.size + self.threshold) * self.scale;
+                let (lower, higher) = self.threshold_range();
+                let (lower, higher) = (lower * self.scale, higher * self.scale);
 
                 if size < lower {
                     size.abs_diff(self.min_size * self.scale)
@@ -444,6 +1443,43 @@ impl DirectoryIndex {
         }
     }
 
+    /// Key to order directories by during lookups, from most to least suitable for the provided
+    /// size and scale. See [`size_distance`](DirectoryIndex::size_distance).
+    pub(crate) fn lookup_order(&self, icon_size: u32, icon_scale: u32) -> (u32, bool) {
+        (
+            self.size_distance(icon_size, icon_scale),
+            self.scale != icon_scale,
+        )
+    }
+
+    /// Like [`size_distance`](DirectoryIndex::size_distance), but takes the file type of the icon
+    /// into account.
+    ///
+    /// Raster images ([FileType::Png](crate::FileType::Png) and [FileType::Xpm](crate::FileType::Xpm))
+    /// found in a [DirectoryType::Scalable] directory cannot actually be scaled without loss,
+    /// so their distance is computed as if the directory was [DirectoryType::Fixed].
+    pub fn icon_size_distance(&self, file_type: FileType, icon_size: u32, icon_scale: u32) -> u32 {
+        if self.size != 0 && self.directory_type == DirectoryType::Scalable && file_type.is_raster()
+        {
+            (self.size * self.scale).abs_diff(icon_size * icon_scale)
+        } else {
+            self.size_distance(icon_size, icon_scale)
+        }
+    }
+
+    /// Like [`matches_size`](DirectoryIndex::matches_size), but takes the file type of the icon
+    /// into account.
+    ///
+    /// Raster images found in a [DirectoryType::Scalable] directory only match the nominal size of
+    /// the directory. See [`icon_size_distance`](DirectoryIndex::icon_size_distance).
+    pub fn matches_icon_size(&self, file_type: FileType, icon_size: u32, icon_scale: u32) -> bool {
+        if self.directory_type == DirectoryType::Scalable && file_type.is_raster() {
+            self.scale == icon_scale && self.size == icon_size && self.size != 0
+        } else {
+            self.matches_size(icon_size, icon_scale)
+        }
+    }
+
     /// Computes whether this directory "supports" icons with the provided `icon_size` (in pixels)
     /// and scale (as a multiple of the size).
     ///
@@ -452,14 +1488,19 @@ impl DirectoryIndex {
     /// - [DirectoryType::Fixed]: Only icons with the same size and scale as the directory match.
     /// - [DirectoryType::Scalable]: Icons with a size between the directory's `min_size` and `max_size`, and equal scale, match.
     /// - [DirectoryType::Threshold]: `icon_size` may only differ by the amount of `threshold` specified by the directory, and scale must match exactly.
+    ///   If the directory declares a `MinSize` or `MaxSize`, `icon_size` must also lie within them.
     ///
     /// When this method returns `true`, the "size distance" of the provided size and scale to the directory's size and scale is considered to be 0.
+    ///
+    /// Directories with a `size` of 0 are invalid, and never match any size.
     pub fn matches_size(&self, icon_size: u32, icon_scale: u32) -> bool {
-        if self.scale != icon_scale {
+        if self.scale != icon_scale || self.size == 0 {
             return false;
         }
 
         match self.directory_type {
+            // the scales are equal, so this is the same as comparing the scaled sizes, which is
+            // what `size_distance` does.
             DirectoryType::Fixed => self.size == icon_size,
             DirectoryType::Scalable => {
                 let DirectoryIndex {
@@ -469,19 +1510,42 @@ impl DirectoryIndex {
                 (min_size..=max_size).contains(&icon_size)
             }
             DirectoryType::Threshold => {
-                let DirectoryIndex {
-                    threshold, size, ..
-                } = *self;
+                let (lower, higher) = self.threshold_range();
 
-                // The icons in this directory can be used if the size differ at most this much from the desired (unscaled) size
-                size.abs_diff(icon_size) <= threshold
+                (lower..=higher).contains(&icon_size)
             }
         }
     }
+
+    /// The largest (unscaled) size the icons of this directory can be used at.
+    fn largest_size(&self) -> u32 {
+        match self.directory_type {
+            DirectoryType::Fixed => self.size,
+            DirectoryType::Scalable => self.max_size,
+            DirectoryType::Threshold => self.threshold_range().1,
+        }
+    }
+
+    /// The (unscaled) sizes the icons of a [Threshold](DirectoryType::Threshold) directory can be
+    /// used at, as an inclusive `(lower, higher)` range.
+    fn threshold_range(&self) -> (u32, u32) {
+        // The icons in this directory can be used if the size differ at most this much from the desired (unscaled) size
+        let lower = self.size.saturating_sub(self.threshold);
+        let higher = self.size.saturating_add(self.threshold);
+
+        // `MinSize` and `MaxSize` are bounds too, but only if the index declares them: their default
+        // of `size` would leave no room for the threshold at all
+        (
+            self.declared_min_size
+                .map_or(lower, |min_size| lower.max(min_size)),
+            self.declared_max_size
+                .map_or(higher, |max_size| higher.min(max_size)),
+        )
+    }
 }
 
 /// The type of image scaling used for an icon theme subdirectory.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum DirectoryType {
     /// Fixed-size images ([FileType::Png](crate::FileType::Png) and [FileType::Xpm](crate::FileType::Xpm)); these icons may not be scaled to any other size.
     Fixed,
@@ -493,6 +1557,17 @@ pub enum DirectoryType {
     Threshold,
 }
 
+impl DirectoryType {
+    /// The name of this type, as used for the `Type` key of an `index.theme` file.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DirectoryType::Fixed => "Fixed",
+            DirectoryType::Scalable => "Scalable",
+            DirectoryType::Threshold => "Threshold",
+        }
+    }
+}
+
 /// The `Default` implementation for `DirectoryType` returns [DirectoryType::Threshold].
 ///
 /// This is because the XDG Icon Theme specification mandates that if the type for a directory is
@@ -518,7 +1593,116 @@ impl TryFrom<&str> for DirectoryType {
     }
 }
 
-fn find_attr<'a>(
+/// The standard contexts of icons, as defined by the
+/// [Icon Naming Specification](https://specifications.freedesktop.org/icon-naming/latest/#context).
+///
+/// Directories of a theme declare the context of their icons in their
+/// [`context`](DirectoryIndex::context), which can be converted with `Context::try_from`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Context {
+    /// Icons for actions, like `edit-copy`.
+    Actions,
+    /// Animated images, like `process-working`.
+    Animations,
+    /// Icons of applications, like `accessories-calculator`.
+    Applications,
+    /// Icons of application categories in menus, like `applications-games`.
+    Categories,
+    /// Icons of hardware devices, like `drive-harddisk`.
+    Devices,
+    /// Small overlays on other icons, like `emblem-symbolic-link`.
+    Emblems,
+    /// Emoticons, like `face-smile`.
+    Emotes,
+    /// Icons of countries and regions, like flags.
+    International,
+    /// Icons of file types, like `text-x-generic`.
+    MimeTypes,
+    /// Icons of locations, like `folder` or `user-home`.
+    Places,
+    /// Icons reporting a status, like `dialog-error`.
+    Status,
+}
+
+impl Context {
+    /// Returns the generic icons to fall back to, in order, if the icon `icon_name` of this context
+    /// is missing. These are the generic icons the Icon Naming Specification defines:
+    ///
+    /// - [`Applications`](Context::Applications): `application-x-executable`.
+    /// - [`Categories`](Context::Categories): `applications-other`.
+    /// - [`MimeTypes`](Context::MimeTypes): the generic icon of the media type, e.g.
+    ///   `image-x-generic` for `image-x-custom` and `application-x-generic` for
+    ///   `application-x-custom`, followed by `text-x-generic`.
+    /// - [`Places`](Context::Places): `folder`.
+    ///
+    /// Other contexts have no generic icon.
+    pub fn generic_icons(&self, icon_name: &str) -> Vec<String> {
+        match self {
+            Context::Applications => vec!["application-x-executable".to_owned()],
+            Context::Categories => vec!["applications-other".to_owned()],
+            Context::MimeTypes => {
+                let media_type = icon_name.split_once('-').map(|(media_type, _)| media_type);
+
+                media_type
+                    .map(|media_type| format!("{media_type}-x-generic"))
+                    .into_iter()
+                    .chain(["text-x-generic".to_owned()])
+                    .collect()
+            }
+            Context::Places => vec!["folder".to_owned()],
+            Context::Actions
+            | Context::Animations
+            | Context::Devices
+            | Context::Emblems
+            | Context::Emotes
+            | Context::International
+            | Context::Status => vec![],
+        }
+    }
+}
+
+impl TryFrom<&str> for Context {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = match value {
+            "Actions" => Context::Actions,
+            "Animations" => Context::Animations,
+            "Applications" => Context::Applications,
+            "Categories" => Context::Categories,
+            "Devices" => Context::Devices,
+            "Emblems" => Context::Emblems,
+            "Emotes" => Context::Emotes,
+            "International" => Context::International,
+            "MimeTypes" => Context::MimeTypes,
+            "Places" => Context::Places,
+            "Status" => Context::Status,
+            _ => return Err(()),
+        };
+
+        Ok(value)
+    }
+}
+
+/// Makes `min..=max` a valid range that includes `size`: an inverted range is swapped, and the
+/// range is then widened to include `size` if it doesn't already.
+fn clamp_size_range(min: u32, max: u32, size: u32) -> (u32, u32) {
+    let (min, max) = if min <= max { (min, max) } else { (max, min) };
+
+    (min.min(size), max.max(size))
+}
+
+fn find_inherits(section: &SectionBytes) -> Result<Vec<String>, std::str::Utf8Error> {
+    let inherits = find_attr(section, "Inherits")?
+        .iter()
+        .flat_map(|s| s.split(',')) // `inherits` is a comma-separated string list
+        .map(Into::into)
+        .collect();
+
+    Ok(inherits)
+}
+
+pub(crate) fn find_attr<'a>(
     section: &'a SectionBytes,
     name: &str,
 ) -> Result<Option<&'a str>, std::str::Utf8Error> {
@@ -539,12 +1723,15 @@ fn find_attr_req<'a>(
 
 #[cfg(test)]
 mod test {
-    use crate::Icons;
-    use crate::icon::FileType;
-    use crate::search::test::test_search;
-    use crate::{DirectoryType, ThemeIndex};
+    use crate::icon::{FileType, IconFile};
+    use crate::search::test::{PROJ_ROOT, test_dir, test_search, write_theme};
+    use crate::{
+        DirectoryType, LookupOutcome, LookupProfile, Theme, ThemeIndex, ThemeInfo, ThemeParseError,
+    };
+    use crate::{IconSearch, Icons};
     use std::error::Error;
     use std::path::Path;
+    use std::sync::Arc;
     use std::time::{Duration, Instant};
 
     #[test]
@@ -552,18 +1739,492 @@ mod test {
         let icons = test_search().search().icons();
 
         let big_ico = icons.find_icon("happy", 64, 1, "TestTheme").unwrap();
-        assert!(big_ico.path().ends_with("TestTheme/32x32/foo/happy.png"));
+        assert!(
+            big_ico
+                .path()
+                .unwrap()
+                .ends_with("TestTheme/32x32/foo/happy.png")
+        );
         assert_eq!(big_ico.file_type(), FileType::Png);
 
         let small_ico = icons.find_icon("happy", 16, 1, "TestTheme").unwrap();
         assert!(
-            small_ico.path().ends_with("TestTheme/16x16/α/happy.png"),
+            small_ico
+                .path()
+                .unwrap()
+                .ends_with("TestTheme/16x16/α/happy.png"),
             "{:?} matches the expected value",
             small_ico.path()
         );
         assert_eq!(small_ico.file_type(), FileType::Png);
     }
 
+    #[test]
+    fn test_find_icon_exact_raster_in_scalable() {
+        let base_dir = test_dir("exact-raster-in-scalable");
+        write_theme(
+            &base_dir,
+            "Mixed",
+            "[Icon Theme]\nName=Mixed\nDirectories=scalable\n\n\
+             [scalable]\nSize=48\nType=Scalable\nMinSize=16\nMaxSize=256\n",
+        );
+        let dir = base_dir.join("Mixed").join("scalable");
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["raster.png", "both.png", "both.svg"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let theme = Theme::load_from_dir(&base_dir.join("Mixed")).unwrap();
+        // raster icons only match the nominal size of a scalable directory
+        assert!(theme.find_icon_exact("raster", 48, 1).is_some());
+        assert!(theme.find_icon_exact("raster", 32, 1).is_none());
+        assert!(theme.find_icon("raster", 32, 1).is_some());
+
+        let icon = theme.find_icon_exact("both", 32, 1).unwrap();
+        assert_eq!(icon.file_type(), FileType::Svg);
+        let icon = theme.find_icon_exact("both", 48, 1).unwrap();
+        assert_eq!(icon.file_type(), FileType::Png);
+    }
+
+    #[test]
+    fn test_infer_directory_types() {
+        let base_dir = test_dir("infer-directory-types");
+        write_theme(
+            &base_dir,
+            "Vectors",
+            "[Icon Theme]\nName=Vectors\nDirectories=48x48,16x16,empty,24x24,32x32,64x64\n\n\
+             [48x48]\nSize=48\n\n[16x16]\nSize=16\n\n[empty]\nSize=32\n\n\
+             [24x24]\nSize=24\nType=Fixed\n\n[32x32]\nSize=32\nType=Threshold\n\n\
+             [64x64]\nSize=64\nMaxSize=64\n",
+        );
+        for (dir, files) in [
+            ("48x48", &["app.svg", "other.svg"][..]),
+            ("16x16", &["app.svg", "app.png"]),
+            ("empty", &[]),
+            ("24x24", &["app.svg"]),
+            ("32x32", &["other.svg"]),
+            ("64x64", &["other.svg"]),
+        ] {
+            let dir = base_dir.join("Vectors").join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            for file in files {
+                std::fs::write(dir.join(file), "").unwrap();
+            }
+        }
+
+        let mut theme = Theme::load_from_dir(&base_dir.join("Vectors")).unwrap();
+        let mut profile = LookupProfile::default();
+        let (_, icon) = theme
+            .find_match_here("app", 128, 1, &[], &mut profile)
+            .unwrap();
+        assert!(icon.path().unwrap().ends_with("48x48/app.svg"));
+        assert_eq!(profile.outcome, LookupOutcome::Closest);
+
+        theme.infer_directory_types();
+
+        let types = theme
+            .info
+            .index
+            .directories
+            .iter()
+            .map(|dir| dir.directory_type)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                DirectoryType::Scalable,
+                DirectoryType::Threshold,
+                DirectoryType::Threshold,
+                DirectoryType::Fixed,
+                // an explicit type is kept, even if it's the default one
+                DirectoryType::Threshold,
+                DirectoryType::Scalable
+            ]
+        );
+
+        let scalable = &theme.info.index.directories[0];
+        assert_eq!((scalable.min_size, scalable.max_size), (1, 512));
+        // declared bounds are kept
+        let bounded = &theme.info.index.directories[5];
+        assert_eq!((bounded.min_size, bounded.max_size), (1, 64));
+
+        // the previously computed directory order was discarded
+        let mut profile = LookupProfile::default();
+        let (_, icon) = theme
+            .find_match_here("app", 128, 1, &[], &mut profile)
+            .unwrap();
+        assert!(icon.path().unwrap().ends_with("48x48/app.svg"));
+        assert_eq!(profile.outcome, LookupOutcome::Exact);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_dangling_index_symlink() {
+        let dir = test_dir("dangling-index-symlink");
+        let (first, second) = (dir.join("first/Theme"), dir.join("second/Theme"));
+        std::fs::create_dir_all(&first).unwrap();
+        std::os::unix::fs::symlink(dir.join("missing.theme"), first.join("index.theme")).unwrap();
+        write_theme(
+            &dir.join("second"),
+            "Theme",
+            "[Icon Theme]\nName=Theme\nDirectories=\n",
+        );
+
+        let info = ThemeInfo::new_from_folders("Theme".into(), vec![first.clone(), second.clone()])
+            .unwrap();
+        assert_eq!(info.index_location, second.join("index.theme"));
+        assert_eq!(info.base_dirs, [first, second]);
+    }
+
+    #[test]
+    fn test_find_icon_files() {
+        let icons = test_search().search().icons();
+        let theme = icons.theme("TestTheme").unwrap();
+
+        let mut sizes = theme
+            .find_icon_files("beautiful sunset")
+            .map(|(dir, icon)| (theme.directory(dir).size, icon.file_type()))
+            .collect::<Vec<_>>();
+        sizes.sort_by_key(|(size, _)| *size);
+        // the .png is preferred over the .xpm in the same directory
+        assert_eq!(sizes, [(64, FileType::Png), (128, FileType::Png)]);
+
+        assert_eq!(theme.find_icon_files("pixel").count(), 0);
+    }
+
+    #[test]
+    fn test_icons_by_size() {
+        let icons = test_search().search().icons();
+        let theme = icons.theme("TestTheme").unwrap();
+
+        let icons_by_size = theme.icons_by_size();
+        let summary = icons_by_size
+            .iter()
+            .map(|(size, icons)| {
+                let icons = icons
+                    .iter()
+                    .map(|icon| (icon.icon_name(), icon.file_type()))
+                    .collect::<Vec<_>>();
+                (*size, icons)
+            })
+            .collect::<Vec<_>>();
+
+        // `16x16/β` has no icons, and icons of parent themes (`pixel`) aren't included
+        assert_eq!(
+            summary,
+            [
+                (16, vec![("happy", FileType::Png)]),
+                (32, vec![("happy", FileType::Png)]),
+                (
+                    64,
+                    vec![
+                        ("beautiful sunset", FileType::Png),
+                        ("beautiful sunset", FileType::Xpm)
+                    ]
+                ),
+                (128, vec![("beautiful sunset", FileType::Png)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_icons_in_directory() {
+        let icons = test_search().search().icons();
+        let theme = icons.theme("TestTheme").unwrap();
+        let directory = |name: &str| {
+            theme
+                .info
+                .index
+                .directories
+                .iter()
+                .find(|dir| dir.directory_name == name)
+                .unwrap()
+        };
+
+        let names = |dir| {
+            let mut names = theme
+                .icons_in_directory(directory(dir))
+                .map(|icon| format!("{}.{}", icon.icon_name(), icon.file_type().ext()))
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names("16x16/α"), ["happy.png"]);
+        assert_eq!(
+            names("UnconventionalDirectoryName/γ"),
+            ["beautiful sunset.png", "beautiful sunset.xpm"]
+        );
+        // the symlinked directory
+        assert_eq!(names("128x128"), ["beautiful sunset.png"]);
+        assert!(names("16x16/β").is_empty());
+    }
+
+    #[test]
+    fn test_base_dir_precedence() {
+        let base_dir = test_dir("base-dir-precedence");
+        let (first, second) = (base_dir.join("first"), base_dir.join("second"));
+        let index = "[Icon Theme]\nName=Split\nDirectories=a,b,s\n\n[a]\nSize=16\n\n[b]\nSize=16\n\n\
+                     [s]\nSize=48\nType=Scalable\nMinSize=16\nMaxSize=64\n";
+        write_theme(&first, "Split", index);
+        // the second index is ignored, as the first one found is used
+        write_theme(&second, "Split", index);
+        for path in [
+            first.join("Split/b/icon.png"),
+            second.join("Split/a/icon.png"),
+            first.join("Split/s/mixed.png"),
+            second.join("Split/s/mixed.svg"),
+        ] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        let icons = IconSearch::new_from(vec![first.clone(), second.clone()])
+            .search()
+            .icons();
+        let theme = icons.theme("Split").unwrap();
+        assert_eq!(
+            theme.info.base_dirs,
+            [first.join("Split"), second.join("Split")]
+        );
+
+        // both match exactly: by default, directory `a` is checked first, in any base directory
+        let icon = theme.find_icon("icon", 16, 1).unwrap();
+        assert_eq!(icon.path(), Some(&*second.join("Split/a/icon.png")));
+
+        let icon = theme.find_icon_base_dir_first("icon", 16, 1).unwrap();
+        assert_eq!(icon.path(), Some(&*first.join("Split/b/icon.png")));
+
+        // without exact matches, it's the same lookup
+        assert_eq!(
+            theme.find_icon_base_dir_first("icon", 32, 1),
+            theme.find_icon("icon", 32, 1)
+        );
+
+        // a raster icon in a scalable directory only matches its nominal size exactly
+        let mut profile = LookupProfile::default();
+        let (_, _, icon) = theme
+            .find_match_base_dir_first("mixed", 32, 1, &mut profile)
+            .unwrap();
+        assert_eq!(icon.path(), Some(&*second.join("Split/s/mixed.svg")));
+        assert_eq!(profile.outcome, LookupOutcome::Exact);
+        assert_eq!(profile.themes_checked, 1);
+        assert!(profile.files_checked > 0);
+
+        let icon = theme.find_icon_base_dir_first("mixed", 48, 1).unwrap();
+        assert_eq!(icon.path(), Some(&*first.join("Split/s/mixed.png")));
+    }
+
+    #[test]
+    fn test_find_icon_formats() {
+        let icons = test_search().search().icons();
+        let theme = icons.theme("TestTheme").unwrap();
+
+        let formats = theme.find_icon_formats("beautiful sunset", 64, 1);
+        let types = formats.iter().map(IconFile::file_type).collect::<Vec<_>>();
+        assert_eq!(types, [FileType::Png, FileType::Xpm]);
+        assert!(formats.iter().all(|icon| {
+            icon.path().is_some_and(|path| {
+                path.parent()
+                    .unwrap()
+                    .ends_with("UnconventionalDirectoryName/γ")
+            })
+        }));
+
+        // the 128x128 directory is the best match, and only holds the png
+        let formats = theme.find_icon_formats("beautiful sunset", 128, 1);
+        assert_eq!(formats.len(), 1);
+        assert_eq!(formats[0].file_type(), FileType::Png);
+
+        // found in a parent theme
+        assert_eq!(theme.find_icon_formats("pixel", 1, 1).len(), 1);
+        assert!(theme.find_icon_formats("not there", 16, 1).is_empty());
+    }
+
+    #[test]
+    fn test_find_icon_sym_aware() {
+        let base_dir = test_dir("sym-aware");
+        for (theme, index, icons) in [
+            ("Parent", "", &["full.png", "qux.png"][..]),
+            ("Child", "Inherits=Parent\n", &["qux-symbolic.png"]),
+        ] {
+            write_theme(
+                &base_dir,
+                theme,
+                &format!(
+                    "[Icon Theme]\nName={theme}\n{index}Directories=16x16\n\n[16x16]\nSize=16\n"
+                ),
+            );
+            let dir = base_dir.join(theme).join("16x16");
+            std::fs::create_dir_all(&dir).unwrap();
+            for icon in icons {
+                std::fs::write(dir.join(icon), "").unwrap();
+            }
+        }
+
+        let icons = IconSearch::new_from(vec![base_dir.to_path_buf()])
+            .search()
+            .icons();
+        let theme = icons.theme("Child").unwrap();
+        let find = |name: &str| {
+            let icon = theme.find_icon_sym_aware(name, 16, 1)?;
+            Some(
+                icon.path()
+                    .unwrap()
+                    .strip_prefix(&base_dir)
+                    .unwrap()
+                    .to_owned(),
+            )
+        };
+
+        assert_eq!(find("full-symbolic"), Some("Parent/16x16/full.png".into()));
+        // an exact match in a parent is preferred over a toggled one in the theme itself
+        assert_eq!(find("qux"), Some("Parent/16x16/qux.png".into()));
+        assert_eq!(
+            find("qux-symbolic"),
+            Some("Child/16x16/qux-symbolic.png".into())
+        );
+        assert_eq!(find("missing"), None);
+        assert_eq!(find("-symbolic"), None);
+        assert_eq!(theme.find_icon("full-symbolic", 16, 1), None);
+    }
+
+    #[test]
+    fn test_find_icon_trace() {
+        let icons = test_search().search().icons();
+        let theme = icons.theme("TestTheme").unwrap();
+
+        let trace = theme.find_icon_trace("pixel", 1, 1).collect::<Vec<_>>();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0], ("TestTheme".to_string(), None));
+        assert_eq!(trace[1].0, "OtherTheme");
+        assert_eq!(trace[1].1, theme.find_icon("pixel", 1, 1));
+
+        let first_hit = theme
+            .find_icon_trace("happy", 16, 1)
+            .find_map(|(_, icon)| icon);
+        assert_eq!(first_hit, theme.find_icon("happy", 16, 1));
+    }
+
+    #[test]
+    fn test_available_size_range() {
+        let icons = test_search().search().icons();
+        let theme = icons.theme("TestTheme").unwrap();
+
+        assert_eq!(theme.available_size_range("happy"), Some(16..=32));
+        // inherited from OtherTheme
+        assert_eq!(theme.available_size_range("pixel"), Some(1..=1));
+        assert_eq!(theme.available_size_range("missing"), None);
+    }
+
+    #[test]
+    fn test_inherits_transitively() {
+        let icons = test_search().search().icons();
+        let test_theme = icons.theme("TestTheme").unwrap();
+        let other_theme = icons.theme("OtherTheme").unwrap();
+
+        assert!(test_theme.inherits_transitively("OtherTheme"));
+        assert!(!test_theme.inherits_transitively("TestTheme"));
+        assert!(!other_theme.inherits_transitively("TestTheme"));
+        assert!(!test_theme.inherits_transitively("hicolor"));
+    }
+
+    #[test]
+    fn test_find_icon_typed() {
+        let icons = test_search().search().icons();
+        let theme = icons.theme("TestTheme").unwrap();
+
+        let icon = theme
+            .find_icon_typed("beautiful sunset", 64, 1, &[FileType::Xpm, FileType::Png])
+            .unwrap();
+        assert_eq!(icon.file_type(), FileType::Xpm);
+
+        let icon = theme
+            .find_icon_typed("beautiful sunset", 64, 1, &[FileType::Png, FileType::Xpm])
+            .unwrap();
+        assert_eq!(icon.file_type(), FileType::Png);
+
+        assert!(
+            theme
+                .find_icon_typed("happy", 16, 1, &[FileType::Svg])
+                .is_none()
+        );
+        assert_eq!(
+            theme.find_icon_typed("happy", 16, 1, &[]),
+            theme.find_icon("happy", 16, 1)
+        );
+    }
+
+    #[test]
+    fn test_load_from_dir() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/test_icons/TestTheme");
+        let theme = Theme::load_from_dir(&path).unwrap();
+
+        assert_eq!(theme.info.internal_name, "TestTheme");
+        assert!(theme.inherits_from.is_empty());
+        assert!(theme.find_icon_here("happy", 16, 1).is_some());
+    }
+
+    #[test]
+    fn test_info_from_index_str() {
+        static INDEX: &str = include_str!("../resources/test_icons/TestTheme/index.theme");
+
+        let base_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/test_icons/TestTheme");
+        let info = ThemeInfo::from_index_str("TestTheme".into(), INDEX, vec![base_dir]).unwrap();
+        assert_eq!(info.index.name, "HelloTestTheme!");
+
+        let theme = Theme::new(info, vec![]);
+        assert!(theme.find_icon("happy", 32, 1).is_some());
+    }
+
+    #[test]
+    fn test_existing_directories() {
+        let icons = test_search().search().icons();
+        let theme = icons.theme("TestTheme").unwrap();
+
+        let test_icons = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/test_icons");
+        let test_icons_alt = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/test_icons_alt");
+        assert_eq!(
+            theme.info.existing_directories(),
+            [
+                test_icons.join("TestTheme/16x16/α"),
+                test_icons.join("TestTheme/32x32/foo"),
+                test_icons_alt.join("TestTheme/UnconventionalDirectoryName/γ"),
+                test_icons_alt.join("TestTheme/128x128"),
+            ]
+        );
+        assert_eq!(
+            theme.existing_directories(),
+            theme.info.existing_directories()
+        );
+    }
+
+    #[test]
+    fn test_directory_exists() {
+        let icons = test_search().search().icons();
+        let theme = icons.theme("TestTheme").unwrap();
+
+        let existing = theme
+            .info
+            .index
+            .directories
+            .iter()
+            .filter(|dir| theme.directory_exists(dir))
+            .map(|dir| dir.directory_name.as_str())
+            .collect::<Vec<_>>();
+
+        // 16x16/β is listed in the index, but doesn't exist. 128x128 is a symlink.
+        assert_eq!(
+            existing,
+            [
+                "16x16/α",
+                "32x32/foo",
+                "UnconventionalDirectoryName/γ",
+                "128x128"
+            ]
+        );
+    }
+
     #[test]
     fn find_all_desktop_entry_icons() {
         let icons = Icons::new();
@@ -656,4 +2317,374 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_fixed_directory_scale() {
+        let base_dir = test_dir("fixed-scale");
+        write_theme(
+            &base_dir,
+            "ScaleTheme",
+            "[Icon Theme]\nName=ScaleTheme\nDirectories=32x32\nScaledDirectories=16x16@2\n\n\
+             [16x16@2]\nSize=16\nScale=2\nType=Fixed\n\n\
+             [32x32]\nSize=32\nType=Fixed\n",
+        );
+        let happy = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/test_icons/TestTheme/32x32/foo/happy.png");
+        for dir in ["16x16@2", "32x32"] {
+            let dir = base_dir.join("ScaleTheme").join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::copy(&happy, dir.join("happy.png")).unwrap();
+        }
+
+        let theme = Theme::load_from_dir(&base_dir.join("ScaleTheme")).unwrap();
+        let scaled_dir = &theme.info.index.directories[0];
+        assert_eq!(scaled_dir.directory_name, "16x16@2");
+
+        // both directories are 32 pixels wide, so they're equally distant from 33x33@1
+        assert_eq!(scaled_dir.size_distance(33, 1), 1);
+        assert_eq!(theme.info.index.directories[1].size_distance(33, 1), 1);
+        assert!(!scaled_dir.matches_size(32, 1));
+
+        // but the directory with the requested scale is preferred:
+        for icon in [
+            theme.find_icon("happy", 33, 1).unwrap(),
+            theme.find_icon_checked("happy", 33, 1).unwrap(),
+        ] {
+            assert!(
+                icon.path().unwrap().ends_with("32x32/happy.png"),
+                "{icon:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_zero_size_directory() {
+        let base_dir = test_dir("zero-size");
+        write_theme(
+            &base_dir,
+            "ZeroTheme",
+            "[Icon Theme]\nName=ZeroTheme\nDirectories=zero,32x32\n\n\
+             [zero]\nSize=0\n\n\
+             [32x32]\nSize=32\n",
+        );
+        let happy = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/test_icons/TestTheme/32x32/foo/happy.png");
+        for dir in ["zero", "32x32"] {
+            let dir = base_dir.join("ZeroTheme").join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::copy(&happy, dir.join("happy.png")).unwrap();
+        }
+
+        let theme = Theme::load_from_dir(&base_dir.join("ZeroTheme")).unwrap();
+        let zero_dir = &theme.info.index.directories[0];
+        assert_eq!(zero_dir.size, 0);
+        assert!(!zero_dir.matches_size(0, 1));
+        assert!(!zero_dir.matches_size(1, 1));
+        assert_eq!(zero_dir.size_distance(1, 1), u32::MAX);
+
+        for icon in [
+            theme.find_icon("happy", 1, 1).unwrap(),
+            theme.find_icon_checked("happy", 1, 1).unwrap(),
+        ] {
+            assert!(
+                icon.path().unwrap().ends_with("32x32/happy.png"),
+                "{icon:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_empty_name() {
+        for name in ["", "   "] {
+            let index =
+                format!("[Icon Theme]\nName={name}\nDirectories=16x16\n\n[16x16]\nSize=16\n");
+
+            assert!(matches!(
+                ThemeIndex::parse(index.as_bytes()),
+                Err(ThemeParseError::EmptyName)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_raster_in_scalable_directory() -> Result<(), Box<dyn Error>> {
+        static EXAMPLE: &str = include_str!("../resources/example.index.theme");
+
+        let index = ThemeIndex::parse(EXAMPLE.as_bytes())?;
+        let scalable = &index.directories[0];
+        assert_eq!(scalable.directory_type, DirectoryType::Scalable);
+
+        assert!(scalable.matches_icon_size(FileType::Svg, 16, 1));
+        assert!(!scalable.matches_icon_size(FileType::Png, 16, 1));
+        assert!(scalable.matches_icon_size(FileType::Png, 48, 1));
+
+        assert_eq!(scalable.icon_size_distance(FileType::Svg, 16, 1), 0);
+        assert_eq!(scalable.icon_size_distance(FileType::Png, 16, 1), 32);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeated_closest_lookups() {
+        const DIRECTORIES: u32 = 1000;
+        const LOOKUPS: u32 = 1000;
+
+        let base_dir = test_dir("repeated-lookups");
+        let names = (1..=DIRECTORIES)
+            .map(|i| format!("{0}x{0}", i * 2))
+            .collect::<Vec<_>>();
+        let mut index = format!("[Icon Theme]\nName=Many\nDirectories={}\n", names.join(","));
+        for (i, name) in names.iter().enumerate() {
+            index += &format!("\n[{name}]\nSize={}\nType=Fixed\n", (i + 1) * 2);
+        }
+        write_theme(&base_dir, "Many", &index);
+
+        let icon_dir = base_dir.join("Many/2x2");
+        std::fs::create_dir_all(&icon_dir).unwrap();
+        std::fs::write(icon_dir.join("app.png"), "").unwrap();
+
+        let theme = Theme::load_from_dir(&base_dir.join("Many")).unwrap();
+
+        // there is no directory of size 3, so every lookup has to find the closest directory
+        let then = Instant::now();
+        for _ in 0..LOOKUPS {
+            let icon = theme.find_icon("app", 3, 1).unwrap();
+            assert_eq!(icon.path().unwrap(), icon_dir.join("app.png"));
+        }
+        let memoized = then.elapsed() / LOOKUPS;
+
+        // forgetting the order before every lookup filters and sorts the directories every time
+        let then = Instant::now();
+        for _ in 0..LOOKUPS {
+            theme.directory_orders.write().unwrap().clear();
+            let icon = theme.find_icon("app", 3, 1).unwrap();
+            assert_eq!(icon.path().unwrap(), icon_dir.join("app.png"));
+        }
+        let recomputed = then.elapsed() / LOOKUPS;
+
+        println!("avg {memoized:?} per lookup with a memoized order, {recomputed:?} without");
+    }
+
+    #[test]
+    fn test_parse_large_index() -> Result<(), Box<dyn Error>> {
+        const DIRECTORIES: u32 = 5000;
+
+        let names = (0..DIRECTORIES)
+            .map(|i| format!("{i}x{i}/apps"))
+            .collect::<Vec<_>>();
+        let mut index = format!(
+            "[Icon Theme]\nName=Large\nDirectories={}\n",
+            names.join(",")
+        );
+        for (i, name) in names.iter().enumerate() {
+            index += &format!("\n[{name}]\nSize={}\n", i + 1);
+        }
+
+        let then = Instant::now();
+        let parsed = ThemeIndex::parse(index.as_bytes())?;
+        println!("parsed {DIRECTORIES} directories in {:?}", then.elapsed());
+
+        assert_eq!(parsed.directories.len(), DIRECTORIES as usize);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_duplicate_directories() -> Result<(), Box<dyn Error>> {
+        let index = ThemeIndex::parse(
+            b"[Icon Theme]\nName=Duplicates\nDirectories=16x16,32x32,16x16\nScaledDirectories=32x32\n\n\
+              [16x16]\nSize=16\n\n[32x32]\nSize=32\n\n[16x16]\nSize=24\nType=Fixed\n",
+        )?;
+
+        let names = index
+            .directories
+            .iter()
+            .map(|d| d.directory_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["16x16", "32x32"]);
+
+        // the last section wins, but keeps the position of the first
+        assert_eq!(index.directories[0].size, 24);
+        assert_eq!(index.directories[0].directory_type, DirectoryType::Fixed);
+        assert!(!index.directories[0].is_scaled_dir);
+        assert!(index.directories[1].is_scaled_dir);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_threshold_size_bounds() -> Result<(), Box<dyn Error>> {
+        let index = ThemeIndex::parse(
+            b"[Icon Theme]\nName=Thresholds\nDirectories=bounded,unbounded\n\n\
+              [bounded]\nSize=32\nThreshold=100\nMinSize=24\nMaxSize=48\n\n\
+              [unbounded]\nSize=32\nThreshold=100\n",
+        )?;
+
+        // the threshold is large, but the size must stay within min_size and max_size
+        let bounded = &index.directories[0];
+        assert_eq!(bounded.directory_type, DirectoryType::Threshold);
+        assert!(bounded.matches_size(24, 1));
+        assert!(bounded.matches_size(48, 1));
+        assert!(!bounded.matches_size(16, 1));
+        assert!(!bounded.matches_size(128, 1));
+        assert_eq!(bounded.size_distance(16, 1), 8);
+        assert_eq!(bounded.size_distance(128, 1), 80);
+
+        // without min_size and max_size, only the threshold applies
+        let unbounded = &index.directories[1];
+        assert!(unbounded.matches_size(16, 1));
+        assert!(unbounded.matches_size(128, 1));
+        assert!(!unbounded.matches_size(133, 1));
+
+        // a single declared bound only replaces its side of the threshold, even if it equals size
+        let index = ThemeIndex::parse(
+            b"[Icon Theme]\nName=Thresholds\nDirectories=min,max\n\n\
+              [min]\nSize=32\nThreshold=8\nMinSize=32\n\n\
+              [max]\nSize=32\nThreshold=8\nMaxSize=32\n",
+        )?;
+        let [min, max] = &index.directories[..] else {
+            panic!("expected two directories");
+        };
+        assert!(!min.matches_size(31, 1));
+        assert!(min.matches_size(40, 1));
+        assert!(max.matches_size(24, 1));
+        assert!(!max.matches_size(33, 1));
+        assert_eq!(ThemeIndex::parse(&index.to_bytes())?, index);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_round_trip() -> Result<(), Box<dyn Error>> {
+        static EXAMPLE: &str = include_str!("../resources/example.index.theme");
+
+        let index = ThemeIndex::parse(EXAMPLE.as_bytes())?;
+        let serialized = index.to_string();
+        assert!(serialized.starts_with("[Icon Theme]\nName=Birch\n"));
+        assert_eq!(ThemeIndex::parse(serialized.as_bytes())?, index);
+        assert_eq!(index.to_bytes(), serialized.as_bytes());
+
+        // scaled directories, and the keys the example leaves out
+        let index = ThemeIndex::parse(
+            b"[Icon Theme]\nName=Scaled\nDirectories=\nScaledDirectories=16@2\n\
+              Hidden=true\nExample=folder\n\n\
+              [16@2]\nSize=16\nType=Threshold\nThreshold=4\n",
+        )?;
+        assert!(index.directories[0].is_scaled_dir);
+        assert_eq!(ThemeIndex::parse(&index.to_bytes())?, index);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid_size_range() -> Result<(), Box<dyn Error>> {
+        let index = ThemeIndex::parse(
+            b"[Icon Theme]\nName=Ranges\nDirectories=inverted,outside\n\n\
+              [inverted]\nSize=48\nType=Scalable\nMinSize=64\nMaxSize=32\n\n\
+              [outside]\nSize=16\nType=Scalable\nMinSize=32\nMaxSize=256\n",
+        )?;
+
+        // the inverted range is swapped, and then widened to include the size
+        let inverted = &index.directories[0];
+        assert_eq!((inverted.min_size, inverted.max_size), (32, 64));
+        assert!(inverted.matches_size(48, 1));
+        assert!(inverted.matches_size(64, 1));
+
+        let outside = &index.directories[1];
+        assert_eq!((outside.min_size, outside.max_size), (16, 256));
+        assert!(outside.matches_size(16, 1));
+
+        assert_eq!(super::clamp_size_range(64, 32, 128), (32, 128));
+        assert_eq!(super::clamp_size_range(16, 32, 24), (16, 32));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_breeze_quirks() -> Result<(), Box<dyn Error>> {
+        let dir = Path::new(PROJ_ROOT).join("resources/test_icons_kde");
+        let index = ThemeIndex::parse(&std::fs::read(dir.join("BreezeLike/index.theme"))?)?;
+
+        // `actions/16@2x` is listed twice, but described once
+        let names = index
+            .directories
+            .iter()
+            .map(|d| d.directory_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["actions/16", "actions/16@2x", "apps/48"]);
+
+        let scaled = &index.directories[1];
+        assert_eq!(scaled.scale, 2);
+        assert!(scaled.is_scaled_dir);
+
+        // only the `Scale` key sets the scale, not the directory name
+        let index =
+            ThemeIndex::parse(b"[Icon Theme]\nName=A\nDirectories=16@2x\n\n[16@2x]\nSize=16\n")?;
+        assert_eq!(index.directories[0].scale, 1);
+
+        let icons = IconSearch::new_empty()
+            .add_directories([dir])
+            .search()
+            .icons();
+
+        let copy = icons.find_icon("edit-copy", 16, 1, "BreezeLike").unwrap();
+        assert!(copy.path().unwrap().ends_with("actions/16/edit-copy.png"));
+
+        let copy = icons.find_icon("edit-copy", 16, 2, "BreezeLike").unwrap();
+        assert!(
+            copy.path()
+                .unwrap()
+                .ends_with("actions/16@2x/edit-copy.png")
+        );
+
+        let file_manager = icons.find_icon("system-file-manager", 48, 1, "BreezeLike");
+        assert!(file_manager.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_scalable_icon() {
+        struct EmptyFileProvider;
+
+        impl crate::FileProvider for EmptyFileProvider {
+            fn exists(&self, _: &Path) -> bool {
+                false
+            }
+
+            fn read_dir(&self, _: &Path) -> std::io::Result<Vec<crate::FileEntry>> {
+                Err(std::io::ErrorKind::NotFound.into())
+            }
+
+            fn read(&self, _: &Path) -> std::io::Result<Vec<u8>> {
+                Err(std::io::ErrorKind::NotFound.into())
+            }
+        }
+
+        let dir = test_dir("find-scalable-icon");
+        let index = |name: &str| {
+            format!(
+                "[Icon Theme]\nName={name}\nDirectories=scalable\n\n\
+                 [scalable]\nSize=16\nType=Scalable\n"
+            )
+        };
+        write_theme(&dir, "Parent", &index("Parent"));
+        std::fs::create_dir_all(dir.join("Parent/scalable")).unwrap();
+        std::fs::write(dir.join("Parent/scalable/vector.svg"), "").unwrap();
+        std::fs::write(dir.join("Parent/scalable/raster.png"), "").unwrap();
+        write_theme(&dir, "Child", &index("Child"));
+
+        let icons = IconSearch::new_from(vec![dir.to_path_buf()])
+            .search()
+            .icons();
+        let parent = icons.theme("Parent").unwrap();
+        let icon = parent.find_scalable_icon("vector").unwrap();
+        assert_eq!(icon.path(), Some(&*dir.join("Parent/scalable/vector.svg")));
+        assert!(parent.find_scalable_icon("raster").is_none());
+
+        // the parent's directories are checked with the parent's file provider
+        let info = ThemeInfo::new_from_folders("Child".into(), vec![dir.join("Child")]).unwrap();
+        let child = Theme::with_file_provider(info, vec![parent], Arc::new(EmptyFileProvider));
+        assert_eq!(child.find_scalable_icon("vector"), Some(icon));
+    }
 }